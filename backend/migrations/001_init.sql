@@ -1,5 +1,7 @@
 -- Factory Knowledge GraphRAG - PostgreSQL初期スキーマ
 -- Phase 1: MVP
+-- 旧 docker/postgres/init で作成済みのボリュームにも適用できるよう、
+-- 既存のテーブル・インデックス・管理者ユーザーはそのまま残す
 
 -- UUID拡張
 CREATE EXTENSION IF NOT EXISTS "pgcrypto";
@@ -7,7 +9,7 @@ CREATE EXTENSION IF NOT EXISTS "pgcrypto";
 -- =====================
 -- users テーブル
 -- =====================
-CREATE TABLE users (
+CREATE TABLE IF NOT EXISTS users (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     username VARCHAR(100) UNIQUE NOT NULL,
     email VARCHAR(255) UNIQUE,
@@ -22,14 +24,14 @@ CREATE TABLE users (
     updated_at TIMESTAMPTZ DEFAULT NOW()
 );
 
-CREATE INDEX idx_users_username ON users(username);
-CREATE INDEX idx_users_role ON users(role);
-CREATE INDEX idx_users_department ON users(department);
+CREATE INDEX IF NOT EXISTS idx_users_username ON users(username);
+CREATE INDEX IF NOT EXISTS idx_users_role ON users(role);
+CREATE INDEX IF NOT EXISTS idx_users_department ON users(department);
 
 -- =====================
 -- sessions テーブル
 -- =====================
-CREATE TABLE sessions (
+CREATE TABLE IF NOT EXISTS sessions (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
     token_hash VARCHAR(255) UNIQUE NOT NULL,
@@ -39,13 +41,13 @@ CREATE TABLE sessions (
     created_at TIMESTAMPTZ DEFAULT NOW()
 );
 
-CREATE INDEX idx_sessions_user_id ON sessions(user_id);
-CREATE INDEX idx_sessions_expires_at ON sessions(expires_at);
+CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_sessions_expires_at ON sessions(expires_at);
 
 -- =====================
 -- documents テーブル
 -- =====================
-CREATE TABLE documents (
+CREATE TABLE IF NOT EXISTS documents (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     file_name VARCHAR(500) NOT NULL,
     file_path VARCHAR(1000),
@@ -67,16 +69,16 @@ CREATE TABLE documents (
     updated_at TIMESTAMPTZ DEFAULT NOW()
 );
 
-CREATE INDEX idx_documents_type ON documents(document_type);
-CREATE INDEX idx_documents_department ON documents(department);
-CREATE INDEX idx_documents_latest ON documents(is_latest);
-CREATE INDEX idx_documents_etl_status ON documents(etl_status);
-CREATE INDEX idx_documents_file_name ON documents(file_name);
+CREATE INDEX IF NOT EXISTS idx_documents_type ON documents(document_type);
+CREATE INDEX IF NOT EXISTS idx_documents_department ON documents(department);
+CREATE INDEX IF NOT EXISTS idx_documents_latest ON documents(is_latest);
+CREATE INDEX IF NOT EXISTS idx_documents_etl_status ON documents(etl_status);
+CREATE INDEX IF NOT EXISTS idx_documents_file_name ON documents(file_name);
 
 -- =====================
 -- chat_sessions テーブル
 -- =====================
-CREATE TABLE chat_sessions (
+CREATE TABLE IF NOT EXISTS chat_sessions (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
     title VARCHAR(500),
@@ -85,12 +87,12 @@ CREATE TABLE chat_sessions (
     updated_at TIMESTAMPTZ DEFAULT NOW()
 );
 
-CREATE INDEX idx_chat_sessions_user_id ON chat_sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_chat_sessions_user_id ON chat_sessions(user_id);
 
 -- =====================
 -- chat_messages テーブル
 -- =====================
-CREATE TABLE chat_messages (
+CREATE TABLE IF NOT EXISTS chat_messages (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     chat_session_id UUID NOT NULL REFERENCES chat_sessions(id) ON DELETE CASCADE,
     role VARCHAR(20) NOT NULL,
@@ -102,13 +104,13 @@ CREATE TABLE chat_messages (
     created_at TIMESTAMPTZ DEFAULT NOW()
 );
 
-CREATE INDEX idx_chat_messages_session ON chat_messages(chat_session_id);
-CREATE INDEX idx_chat_messages_created ON chat_messages(created_at);
+CREATE INDEX IF NOT EXISTS idx_chat_messages_session ON chat_messages(chat_session_id);
+CREATE INDEX IF NOT EXISTS idx_chat_messages_created ON chat_messages(created_at);
 
 -- =====================
 -- feedback テーブル
 -- =====================
-CREATE TABLE feedback (
+CREATE TABLE IF NOT EXISTS feedback (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     chat_message_id UUID NOT NULL REFERENCES chat_messages(id) ON DELETE CASCADE,
     user_id UUID NOT NULL REFERENCES users(id),
@@ -117,13 +119,13 @@ CREATE TABLE feedback (
     created_at TIMESTAMPTZ DEFAULT NOW()
 );
 
-CREATE INDEX idx_feedback_message ON feedback(chat_message_id);
-CREATE INDEX idx_feedback_rating ON feedback(rating);
+CREATE INDEX IF NOT EXISTS idx_feedback_message ON feedback(chat_message_id);
+CREATE INDEX IF NOT EXISTS idx_feedback_rating ON feedback(rating);
 
 -- =====================
 -- etl_jobs テーブル
 -- =====================
-CREATE TABLE etl_jobs (
+CREATE TABLE IF NOT EXISTS etl_jobs (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     document_id UUID REFERENCES documents(id),
     job_type VARCHAR(50) NOT NULL,
@@ -135,13 +137,13 @@ CREATE TABLE etl_jobs (
     created_at TIMESTAMPTZ DEFAULT NOW()
 );
 
-CREATE INDEX idx_etl_jobs_status ON etl_jobs(status);
-CREATE INDEX idx_etl_jobs_document ON etl_jobs(document_id);
+CREATE INDEX IF NOT EXISTS idx_etl_jobs_status ON etl_jobs(status);
+CREATE INDEX IF NOT EXISTS idx_etl_jobs_document ON etl_jobs(document_id);
 
 -- =====================
 -- audit_log テーブル
 -- =====================
-CREATE TABLE audit_log (
+CREATE TABLE IF NOT EXISTS audit_log (
     id BIGSERIAL PRIMARY KEY,
     user_id UUID REFERENCES users(id),
     action VARCHAR(100) NOT NULL,
@@ -152,10 +154,10 @@ CREATE TABLE audit_log (
     created_at TIMESTAMPTZ DEFAULT NOW()
 );
 
-CREATE INDEX idx_audit_log_user ON audit_log(user_id);
-CREATE INDEX idx_audit_log_action ON audit_log(action);
-CREATE INDEX idx_audit_log_created ON audit_log(created_at);
-CREATE INDEX idx_audit_log_resource ON audit_log(resource_type, resource_id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_user ON audit_log(user_id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_action ON audit_log(action);
+CREATE INDEX IF NOT EXISTS idx_audit_log_created ON audit_log(created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_log_resource ON audit_log(resource_type, resource_id);
 
 -- =====================
 -- 初期管理者ユーザー (パスワード: admin123 のbcryptハッシュ)
@@ -169,4 +171,5 @@ VALUES (
     'System Administrator',
     'admin',
     'IT'
-);
+)
+ON CONFLICT (username) DO NOTHING;
@@ -0,0 +1,16 @@
+-- Factory Knowledge GraphRAG - リフレッシュトークン管理
+-- リフレッシュトークンのローテーションと再利用検知
+
+-- =====================
+-- refresh_tokens テーブル
+-- =====================
+CREATE TABLE refresh_tokens (
+    jti UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_refresh_tokens_user_id ON refresh_tokens(user_id);
+CREATE INDEX idx_refresh_tokens_expires_at ON refresh_tokens(expires_at);
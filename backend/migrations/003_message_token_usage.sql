@@ -0,0 +1,7 @@
+-- Factory Knowledge GraphRAG - 回答ごとのトークン使用量
+-- token_count は合計トークン数。prompt_tokens が NULL の場合は LLM から使用量が返らず、
+-- completion_tokens を回答の文字数から推定したことを示す
+
+ALTER TABLE chat_messages
+    ADD COLUMN prompt_tokens INTEGER,
+    ADD COLUMN completion_tokens INTEGER;
@@ -0,0 +1,22 @@
+-- Factory Knowledge GraphRAG - 認証イベントの監査ログ
+-- ログイン・ログアウト・トークンリフレッシュ・アカウント変更を audit_log に記録する。
+-- 追記専用: UPDATE / DELETE / TRUNCATE はトリガーで拒否する
+
+ALTER TABLE audit_log
+    ADD COLUMN outcome VARCHAR(20) NOT NULL DEFAULT 'success'
+        CHECK (outcome IN ('success', 'failure')),
+    ADD COLUMN user_agent VARCHAR(512);
+
+CREATE FUNCTION audit_log_reject_change() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_no_update_delete
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_reject_change();
+
+CREATE TRIGGER audit_log_no_truncate
+    BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_log_reject_change();
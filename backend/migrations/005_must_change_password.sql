@@ -0,0 +1,5 @@
+-- Factory Knowledge GraphRAG - パスワード変更の強制
+-- 管理者がパスワードをリセットしたアカウントは、ユーザー自身が変更するまで true になる
+
+ALTER TABLE users
+    ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,5 @@
+-- Factory Knowledge GraphRAG - 会話履歴のカーソルページネーション用インデックス
+-- (user_id, updated_at, id) の順で走査し、OFFSET による読み飛ばしを避ける
+
+CREATE INDEX idx_chat_sessions_user_updated
+    ON chat_sessions(user_id, updated_at DESC, id DESC);
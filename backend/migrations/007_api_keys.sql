@@ -0,0 +1,22 @@
+-- Factory Knowledge GraphRAG - サービスクライアント向け API キー
+-- スクリプトや CI が JWT の代わりに X-API-Key ヘッダーで認証する。
+-- キー本体は保存せず SHA-256 ハッシュのみを持つ（平文は発行時に一度だけ返す）
+
+-- =====================
+-- api_keys テーブル
+-- =====================
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash VARCHAR(64) NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMPTZ,
+    revoked BOOLEAN NOT NULL DEFAULT false,
+    last_used_at TIMESTAMPTZ,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id);
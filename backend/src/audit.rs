@@ -0,0 +1,239 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::convert::Infallible;
+use std::net::IpAddr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::client_ip;
+use crate::AppState;
+
+/// Longest `User-Agent` stored, matching the `audit_log.user_agent` column
+const MAX_USER_AGENT_CHARS: usize = 512;
+
+/// Authentication and account events recorded in `audit_log`
+pub enum AuditEvent {
+    Login,
+    LoginFailed,
+    /// Login attempted while the account is locked out
+    LoginLocked,
+    Logout,
+    LogoutAll,
+    TokenRefresh,
+    /// A revoked or already rotated refresh token was presented
+    TokenRefreshFailed,
+    /// A user deleted their own account
+    AccountDeleted,
+    UserDeactivated {
+        user_id: Uuid,
+    },
+    UserReactivated {
+        user_id: Uuid,
+    },
+    /// An admin reset another user's password
+    PasswordReset {
+        user_id: Uuid,
+    },
+    /// A user changed their own password
+    PasswordChanged,
+    /// An admin issued an API key acting as `user_id`
+    ApiKeyCreated {
+        user_id: Uuid,
+        key_id: Uuid,
+    },
+    /// An admin revoked an API key of `user_id`
+    ApiKeyRevoked {
+        user_id: Uuid,
+        key_id: Uuid,
+    },
+    /// A chat or search query matched the query blocklist
+    QueryBlocked {
+        pattern: String,
+    },
+}
+
+impl AuditEvent {
+    fn action(&self) -> &'static str {
+        match self {
+            AuditEvent::Login => "LOGIN",
+            AuditEvent::LoginFailed => "LOGIN_FAILED",
+            AuditEvent::LoginLocked => "LOGIN_LOCKED",
+            AuditEvent::Logout => "LOGOUT",
+            AuditEvent::LogoutAll => "LOGOUT_ALL",
+            AuditEvent::TokenRefresh => "TOKEN_REFRESH",
+            AuditEvent::TokenRefreshFailed => "TOKEN_REFRESH_FAILED",
+            AuditEvent::AccountDeleted => "ACCOUNT_DELETED",
+            AuditEvent::UserDeactivated { .. } => "USER_DEACTIVATED",
+            AuditEvent::UserReactivated { .. } => "USER_REACTIVATED",
+            AuditEvent::PasswordReset { .. } => "PASSWORD_RESET",
+            AuditEvent::PasswordChanged => "PASSWORD_CHANGED",
+            AuditEvent::ApiKeyCreated { .. } => "API_KEY_CREATED",
+            AuditEvent::ApiKeyRevoked { .. } => "API_KEY_REVOKED",
+            AuditEvent::QueryBlocked { .. } => "QUERY_BLOCKED",
+        }
+    }
+
+    fn outcome(&self) -> &'static str {
+        match self {
+            AuditEvent::LoginFailed
+            | AuditEvent::LoginLocked
+            | AuditEvent::TokenRefreshFailed
+            | AuditEvent::QueryBlocked { .. } => "failure",
+            _ => "success",
+        }
+    }
+
+    /// The account an admin action was applied to
+    fn target_user_id(&self) -> Option<Uuid> {
+        match self {
+            AuditEvent::UserDeactivated { user_id }
+            | AuditEvent::UserReactivated { user_id }
+            | AuditEvent::PasswordReset { user_id }
+            | AuditEvent::ApiKeyCreated { user_id, .. }
+            | AuditEvent::ApiKeyRevoked { user_id, .. } => Some(*user_id),
+            _ => None,
+        }
+    }
+
+    /// Event-specific fields added to `details`
+    fn extra_details(&self) -> Option<Value> {
+        match self {
+            AuditEvent::ApiKeyCreated { key_id, .. } | AuditEvent::ApiKeyRevoked { key_id, .. } => {
+                Some(json!({ "api_key_id": key_id }))
+            }
+            AuditEvent::QueryBlocked { pattern } => Some(json!({ "pattern": pattern })),
+            _ => None,
+        }
+    }
+}
+
+/// Who performed an audited action
+pub struct Actor<'a> {
+    /// `None` for a failed login with an unknown username
+    pub user_id: Option<Uuid>,
+    pub username: &'a str,
+}
+
+/// Client details recorded with each audit entry
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl FromRequestParts<Arc<AppState>> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|ua| ua.chars().take(MAX_USER_AGENT_CHARS).collect());
+
+        Ok(Self {
+            ip: client_ip::from_parts(parts, &state.config.trusted_proxies),
+            user_agent,
+        })
+    }
+}
+
+/// Append an entry to `audit_log`.
+///
+/// Failures are logged rather than returned, so an audit outage doesn't lock
+/// users out. The table itself rejects updates and deletes.
+pub async fn record(db: &PgPool, client: &ClientInfo, event: AuditEvent, actor: Actor<'_>) {
+    let target_user_id = event.target_user_id();
+    let mut details = json!({
+        "username": actor.username,
+        "request_id": crate::request_id::current(),
+    });
+    if let (Some(details), Some(Value::Object(extra))) =
+        (details.as_object_mut(), event.extra_details())
+    {
+        details.extend(extra);
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO audit_log \
+         (user_id, action, outcome, resource_type, resource_id, details, ip_address, user_agent) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7::inet, $8)",
+    )
+    .bind(actor.user_id)
+    .bind(event.action())
+    .bind(event.outcome())
+    .bind(target_user_id.map(|_| "user"))
+    .bind(target_user_id.map(|id| id.to_string()))
+    .bind(details)
+    .bind(client.ip.map(|ip| ip.to_string()))
+    .bind(&client.user_agent)
+    .execute(db)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!(
+            action = event.action(),
+            "Failed to write audit log entry: {}",
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn failed_login_is_recorded() {
+        let app = test_support::spawn(test_support::config()).await;
+        let user = app.user("user").await;
+
+        let response = app
+            .client
+            .post(app.url("/api/v1/auth/login"))
+            .header(reqwest::header::USER_AGENT, "audit-test")
+            .json(&json!({ "username": user.username, "password": "Wrong-password-1" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let (user_id, action, outcome, user_agent): (Option<Uuid>, String, String, Option<String>) =
+            sqlx::query_as(
+                "SELECT user_id, action, outcome, user_agent FROM audit_log \
+                 WHERE details->>'username' = $1",
+            )
+            .bind(&user.username)
+            .fetch_one(&app.state.db)
+            .await
+            .unwrap();
+        assert_eq!(user_id, Some(user.id));
+        assert_eq!(action, "LOGIN_FAILED");
+        assert_eq!(outcome, "failure");
+        assert_eq!(user_agent.as_deref(), Some("audit-test"));
+    }
+
+    #[tokio::test]
+    async fn entries_cannot_be_removed() {
+        let app = test_support::spawn(test_support::config()).await;
+        let user = app.user("user").await;
+        app.login(&user).await;
+
+        let deleted = sqlx::query("DELETE FROM audit_log WHERE user_id = $1")
+            .bind(user.id)
+            .execute(&app.state.db)
+            .await;
+        assert!(deleted.is_err());
+    }
+}
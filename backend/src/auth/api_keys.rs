@@ -0,0 +1,82 @@
+use axum::http::{HeaderMap, HeaderName};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+pub static API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+
+/// Marks the gateway's keys, so a leaked one is easy to recognise
+const KEY_PREFIX: &str = "grk_";
+
+/// Random bytes in a key
+const KEY_BYTES: usize = 32;
+
+/// Characters of the key kept in `api_keys.key_prefix` for listings
+const DISPLAY_PREFIX_CHARS: usize = 12;
+
+/// A newly generated key; `plaintext` is shown once and never stored
+pub struct NewKey {
+    pub plaintext: String,
+    pub prefix: String,
+    pub hash: String,
+}
+
+/// Owner and permissions of a key presented in `X-API-Key`
+#[derive(Debug, FromRow)]
+pub struct ResolvedKey {
+    pub key_id: Uuid,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: String,
+    pub must_change_password: bool,
+}
+
+pub fn generate() -> NewKey {
+    let bytes: [u8; KEY_BYTES] = rand::random();
+    let plaintext = format!("{}{}", KEY_PREFIX, URL_SAFE_NO_PAD.encode(bytes));
+    NewKey {
+        prefix: plaintext.chars().take(DISPLAY_PREFIX_CHARS).collect(),
+        hash: hash(&plaintext),
+        plaintext,
+    }
+}
+
+/// Keys are long and random, so a fast hash is enough to make the stored
+/// values useless to someone reading the table.
+pub fn hash(key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(key);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Key from an `X-API-Key` header; a missing or empty header yields `None`.
+pub fn from_headers(headers: &HeaderMap) -> Option<&str> {
+    let key = headers.get(&API_KEY_HEADER)?.to_str().ok()?.trim();
+    (!key.is_empty()).then_some(key)
+}
+
+/// Look up a presented key, recording that it was used.
+///
+/// Unknown, revoked and expired keys yield `None`, as do keys of deactivated users.
+pub async fn resolve(db: &PgPool, key: &str) -> Result<Option<ResolvedKey>, AppError> {
+    let resolved = sqlx::query_as::<_, ResolvedKey>(
+        "UPDATE api_keys k SET last_used_at = NOW() \
+         FROM users u \
+         WHERE k.key_hash = $1 AND k.revoked = false \
+           AND (k.expires_at IS NULL OR k.expires_at > NOW()) \
+           AND u.id = k.user_id AND u.is_active = true \
+         RETURNING k.id AS key_id, k.scopes, k.expires_at, u.id AS user_id, u.username, u.role, \
+                   u.must_change_password",
+    )
+    .bind(hash(key))
+    .fetch_optional(db)
+    .await?;
+
+    Ok(resolved)
+}
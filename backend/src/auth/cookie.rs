@@ -0,0 +1,42 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+
+const REFRESH_COOKIE_NAME: &str = "refresh_token";
+
+/// The browser only sends the cookie to the auth endpoints that use it
+const REFRESH_COOKIE_PATH: &str = "/api/v1/auth";
+
+/// `Set-Cookie` value carrying a refresh token, readable only by the server.
+///
+/// `SameSite=Strict` assumes the frontend is served from the same site as the API.
+pub fn refresh_cookie(token: &str, max_age_secs: i64, secure: bool) -> HeaderValue {
+    build(token, max_age_secs, secure)
+}
+
+/// `Set-Cookie` value that removes the refresh token cookie.
+pub fn clear_refresh_cookie(secure: bool) -> HeaderValue {
+    build("", 0, secure)
+}
+
+fn build(value: &str, max_age_secs: i64, secure: bool) -> HeaderValue {
+    let mut cookie = format!(
+        "{}={}; Path={}; Max-Age={}; HttpOnly; SameSite=Strict",
+        REFRESH_COOKIE_NAME, value, REFRESH_COOKIE_PATH, max_age_secs
+    );
+    if secure {
+        cookie.push_str("; Secure");
+    }
+    HeaderValue::from_str(&cookie).expect("JWTs are valid cookie values")
+}
+
+/// The refresh token sent in the request's `Cookie` headers, if any.
+pub fn refresh_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == REFRESH_COOKIE_NAME)
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+}
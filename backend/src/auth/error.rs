@@ -0,0 +1,61 @@
+use jsonwebtoken::errors::{Error as JwtError, ErrorKind};
+
+use crate::error::AppError;
+
+/// Why a JWT or API key was rejected, or a JWT could not be issued
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    /// `exp` has passed, beyond the allowed leeway
+    #[error("Token has expired")]
+    Expired,
+
+    /// Correctly signed, but a claim check failed (`iss`, `aud`, `nbf`, `iat`,
+    /// `sub`, `typ`, algorithm)
+    #[error("Invalid token")]
+    Invalid,
+
+    /// Not a decodable JWT
+    #[error("Malformed token")]
+    Malformed,
+
+    /// The signature doesn't match the verification key
+    #[error("Invalid token signature")]
+    Signature,
+
+    /// An `X-API-Key` that is unknown, revoked or expired, or whose owner is deactivated
+    #[error("Invalid API key")]
+    ApiKey,
+
+    /// The configured key could not sign or verify the token; a server-side problem
+    #[error("JWT key error: {0}")]
+    Key(JwtError),
+}
+
+impl From<JwtError> for AuthError {
+    fn from(e: JwtError) -> Self {
+        match e.kind() {
+            ErrorKind::ExpiredSignature => AuthError::Expired,
+            ErrorKind::InvalidSignature => AuthError::Signature,
+            ErrorKind::InvalidToken
+            | ErrorKind::Base64(_)
+            | ErrorKind::Json(_)
+            | ErrorKind::Utf8(_) => AuthError::Malformed,
+            ErrorKind::InvalidIssuer
+            | ErrorKind::InvalidAudience
+            | ErrorKind::InvalidSubject
+            | ErrorKind::ImmatureSignature
+            | ErrorKind::InvalidAlgorithm
+            | ErrorKind::MissingRequiredClaim(_) => AuthError::Invalid,
+            _ => AuthError::Key(e),
+        }
+    }
+}
+
+impl From<AuthError> for AppError {
+    fn from(e: AuthError) -> Self {
+        match e {
+            AuthError::Key(e) => AppError::Internal(format!("JWT key error: {}", e)),
+            e => AppError::InvalidToken(e),
+        }
+    }
+}
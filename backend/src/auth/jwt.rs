@@ -1,65 +1,320 @@
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::error::AuthError;
+use crate::config::Config;
+
+/// What a token may be used for, carried in its `typ` claim
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenType {
+    /// Sent as `Authorization: Bearer` to call the API
+    Access,
+    /// Only exchanged at `/auth/refresh` for a new token pair
+    Refresh,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String,
     pub username: String,
     pub role: String,
+    pub typ: TokenType,
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
     pub exp: i64,
     pub iat: i64,
+    /// `iat` in milliseconds, so a revocation cutoff can tell apart tokens
+    /// issued within the same second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat_ms: Option<i64>,
 }
 
-pub fn create_access_token(
+impl Claims {
+    /// When the token was issued, in milliseconds. A token minted without
+    /// `iat_ms` counts as issued at the end of its `iat` second.
+    pub fn issued_at_ms(&self) -> i64 {
+        self.iat_ms.unwrap_or(self.iat * 1000 + 999)
+    }
+}
+
+/// Signing and verification keys, loaded once at startup.
+///
+/// RS256 is used when `JWT_PRIVATE_KEY_PATH` / `JWT_PUBLIC_KEY_PATH` are set so
+/// other services can verify tokens with only the public key; otherwise HS256
+/// with `JWT_SECRET`.
+pub struct JwtKeys {
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    issuer: String,
+    audience: String,
+    leeway_secs: u64,
+}
+
+impl JwtKeys {
+    pub fn from_config(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let (algorithm, encoding, decoding) =
+            match (&config.jwt_private_key_path, &config.jwt_public_key_path) {
+                (Some(private_path), Some(public_path)) => (
+                    Algorithm::RS256,
+                    EncodingKey::from_rsa_pem(&read_key(private_path)?)?,
+                    DecodingKey::from_rsa_pem(&read_key(public_path)?)?,
+                ),
+                _ => (
+                    Algorithm::HS256,
+                    EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+                    DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+                ),
+            };
+
+        Ok(Self {
+            algorithm,
+            encoding,
+            decoding,
+            issuer: config.jwt_issuer.clone(),
+            audience: config.jwt_audience.clone(),
+            leeway_secs: config.jwt_leeway_secs,
+        })
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+}
+
+fn read_key(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("failed to read JWT key {}: {}", path, e))
+}
+
+fn claims(
     user_id: Uuid,
     username: &str,
     role: &str,
-    secret: &str,
+    typ: TokenType,
+    keys: &JwtKeys,
     expiry_secs: i64,
-) -> Result<String, jsonwebtoken::errors::Error> {
+) -> Claims {
     let now = Utc::now();
-    let claims = Claims {
+    Claims {
         sub: user_id.to_string(),
         username: username.to_string(),
         role: role.to_string(),
+        typ,
+        jti: Uuid::new_v4().to_string(),
+        iss: keys.issuer.clone(),
+        aud: keys.audience.clone(),
         iat: now.timestamp(),
+        iat_ms: Some(now.timestamp_millis()),
         exp: (now + Duration::seconds(expiry_secs)).timestamp(),
-    };
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )
+    }
 }
 
+pub fn create_access_token(
+    user_id: Uuid,
+    username: &str,
+    role: &str,
+    keys: &JwtKeys,
+    expiry_secs: i64,
+) -> Result<String, AuthError> {
+    let claims = claims(
+        user_id,
+        username,
+        role,
+        TokenType::Access,
+        keys,
+        expiry_secs,
+    );
+    encode(&Header::new(keys.algorithm), &claims, &keys.encoding).map_err(AuthError::Key)
+}
+
+/// Returns the encoded token together with its claims so the caller can persist the `jti`.
 pub fn create_refresh_token(
     user_id: Uuid,
     username: &str,
     role: &str,
-    secret: &str,
-) -> Result<String, jsonwebtoken::errors::Error> {
-    let now = Utc::now();
-    let claims = Claims {
-        sub: user_id.to_string(),
-        username: username.to_string(),
-        role: role.to_string(),
-        iat: now.timestamp(),
-        exp: (now + Duration::days(7)).timestamp(),
-    };
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )
+    keys: &JwtKeys,
+    expiry_secs: i64,
+) -> Result<(String, Claims), AuthError> {
+    let claims = claims(
+        user_id,
+        username,
+        role,
+        TokenType::Refresh,
+        keys,
+        expiry_secs,
+    );
+    let token =
+        encode(&Header::new(keys.algorithm), &claims, &keys.encoding).map_err(AuthError::Key)?;
+    Ok((token, claims))
 }
 
-pub fn verify_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
-    )?;
-    Ok(token_data.claims)
+/// Only the configured algorithm is accepted, so an HS256 token can't be
+/// forged against the RSA public key. `iss` and `aud` must be present and
+/// match, which rejects tokens minted by other services sharing the key.
+/// Access and refresh tokens share the key, so `typ` must be `expected`.
+///
+/// `exp`, `nbf` and `iat` are checked with `leeway_secs` of tolerance for
+/// clock skew between the minting and verifying hosts.
+pub fn verify_token(token: &str, keys: &JwtKeys, expected: TokenType) -> Result<Claims, AuthError> {
+    let mut validation = Validation::new(keys.algorithm);
+    validation.set_issuer(&[&keys.issuer]);
+    validation.set_audience(&[&keys.audience]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+    validation.leeway = keys.leeway_secs;
+    validation.validate_nbf = true;
+
+    let claims = decode::<Claims>(token, &keys.decoding, &validation)?.claims;
+    if claims.typ != expected {
+        return Err(AuthError::Invalid);
+    }
+
+    // jsonwebtoken doesn't check `iat`; reject tokens issued in the future
+    if claims.iat > Utc::now().timestamp() + keys.leeway_secs as i64 {
+        return Err(AuthError::Invalid);
+    }
+
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn keys() -> JwtKeys {
+        JwtKeys::from_config(&test_support::config()).unwrap()
+    }
+
+    #[test]
+    fn access_token_verifies_only_as_access() {
+        let keys = keys();
+        let token = create_access_token(Uuid::new_v4(), "alice", "user", &keys, 60).unwrap();
+
+        let claims = verify_token(&token, &keys, TokenType::Access).unwrap();
+        assert_eq!(claims.typ, TokenType::Access);
+        assert!(matches!(
+            verify_token(&token, &keys, TokenType::Refresh),
+            Err(AuthError::Invalid)
+        ));
+    }
+
+    #[test]
+    fn refresh_token_verifies_only_as_refresh() {
+        let keys = keys();
+        let (token, _) = create_refresh_token(Uuid::new_v4(), "alice", "user", &keys, 60).unwrap();
+
+        assert!(verify_token(&token, &keys, TokenType::Refresh).is_ok());
+        assert!(matches!(
+            verify_token(&token, &keys, TokenType::Access),
+            Err(AuthError::Invalid)
+        ));
+    }
+
+    fn rs256_keys() -> JwtKeys {
+        let testdata = concat!(env!("CARGO_MANIFEST_DIR"), "/src/auth/testdata");
+        let mut config = test_support::config();
+        config.jwt_private_key_path = Some(format!("{}/rs256_private.pem", testdata));
+        config.jwt_public_key_path = Some(format!("{}/rs256_public.pem", testdata));
+        JwtKeys::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn rs256_tokens_verify_with_the_public_key() {
+        let keys = rs256_keys();
+        assert_eq!(keys.algorithm(), Algorithm::RS256);
+        let token = create_access_token(Uuid::new_v4(), "alice", "user", &keys, 60).unwrap();
+
+        assert_eq!(
+            jsonwebtoken::decode_header(&token).unwrap().alg,
+            Algorithm::RS256
+        );
+        assert_eq!(
+            verify_token(&token, &keys, TokenType::Access)
+                .unwrap()
+                .username,
+            "alice"
+        );
+    }
+
+    #[test]
+    fn rs256_keys_reject_hs256_tokens() {
+        let rs256 = rs256_keys();
+        let hs256 = keys();
+        let token = create_access_token(Uuid::new_v4(), "alice", "user", &hs256, 60).unwrap();
+
+        assert!(matches!(
+            verify_token(&token, &rs256, TokenType::Access),
+            Err(AuthError::Invalid)
+        ));
+    }
+
+    #[test]
+    fn token_for_another_audience_is_rejected() {
+        let mut config = test_support::config();
+        config.jwt_audience = "another-service".to_string();
+        let other = JwtKeys::from_config(&config).unwrap();
+        let token = create_access_token(Uuid::new_v4(), "alice", "user", &other, 60).unwrap();
+
+        assert!(matches!(
+            verify_token(&token, &keys(), TokenType::Access),
+            Err(AuthError::Invalid)
+        ));
+    }
+
+    #[test]
+    fn token_from_another_issuer_is_rejected() {
+        let mut config = test_support::config();
+        config.jwt_issuer = "another-issuer".to_string();
+        let other = JwtKeys::from_config(&config).unwrap();
+        let token = create_access_token(Uuid::new_v4(), "alice", "user", &other, 60).unwrap();
+
+        assert!(matches!(
+            verify_token(&token, &keys(), TokenType::Access),
+            Err(AuthError::Invalid)
+        ));
+    }
+
+    #[test]
+    fn recently_expired_token_is_accepted_within_the_leeway() {
+        let mut config = test_support::config();
+        config.jwt_leeway_secs = 60;
+        let keys = JwtKeys::from_config(&config).unwrap();
+
+        let recent = create_access_token(Uuid::new_v4(), "alice", "user", &keys, -5).unwrap();
+        assert!(verify_token(&recent, &keys, TokenType::Access).is_ok());
+
+        let stale = create_access_token(Uuid::new_v4(), "alice", "user", &keys, -120).unwrap();
+        assert!(matches!(
+            verify_token(&stale, &keys, TokenType::Access),
+            Err(AuthError::Expired)
+        ));
+    }
+
+    #[test]
+    fn tampered_token_fails_the_signature_check() {
+        let keys = keys();
+        let token = create_access_token(Uuid::new_v4(), "alice", "user", &keys, 60).unwrap();
+        let forged = create_access_token(Uuid::new_v4(), "mallory", "admin", &keys, 60).unwrap();
+
+        // alice's header and signature around mallory's claims
+        let parts: Vec<&str> = token.split('.').collect();
+        let forged_claims = forged.split('.').nth(1).unwrap();
+        let tampered = format!("{}.{}.{}", parts[0], forged_claims, parts[2]);
+        assert!(matches!(
+            verify_token(&tampered, &keys, TokenType::Access),
+            Err(AuthError::Signature)
+        ));
+    }
+
+    #[test]
+    fn undecodable_token_is_malformed() {
+        assert!(matches!(
+            verify_token("not-a-jwt", &keys(), TokenType::Access),
+            Err(AuthError::Malformed)
+        ));
+    }
 }
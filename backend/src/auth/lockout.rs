@@ -0,0 +1,51 @@
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+const KEY_PREFIX: &str = "auth:login_failures:";
+
+/// Keyed on the submitted username whether or not the account exists,
+/// so lockout behaviour doesn't reveal which usernames are valid.
+fn key(username: &str) -> String {
+    format!("{}{}", KEY_PREFIX, username.to_lowercase())
+}
+
+/// Return the remaining lockout in seconds if `username` has reached `max_attempts`.
+pub async fn locked_for(
+    redis: &ConnectionManager,
+    username: &str,
+    max_attempts: u32,
+) -> Result<Option<u64>, redis::RedisError> {
+    let mut conn = redis.clone();
+    let failures: Option<u32> = conn.get(key(username)).await?;
+    if failures.unwrap_or(0) < max_attempts {
+        return Ok(None);
+    }
+
+    let ttl: i64 = conn.ttl(key(username)).await?;
+    Ok(Some(ttl.max(1) as u64))
+}
+
+/// Count a failed login.
+///
+/// The window starts at the first failure and lasts `lockout_secs`; the
+/// failure that reaches `max_attempts` restarts it, so a lock always lasts
+/// the full cooldown however late in the window it was triggered.
+pub async fn record_failure(
+    redis: &ConnectionManager,
+    username: &str,
+    max_attempts: u32,
+    lockout_secs: u64,
+) -> Result<u32, redis::RedisError> {
+    let mut conn = redis.clone();
+    let failures: u32 = conn.incr(key(username), 1).await?;
+    if failures == 1 || failures >= max_attempts {
+        conn.expire::<_, ()>(key(username), lockout_secs as i64)
+            .await?;
+    }
+    Ok(failures)
+}
+
+pub async fn reset(redis: &ConnectionManager, username: &str) -> Result<(), redis::RedisError> {
+    let mut conn = redis.clone();
+    conn.del(key(username)).await
+}
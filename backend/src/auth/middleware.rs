@@ -1,13 +1,17 @@
 use axum::{
     extract::{Request, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     middleware::Next,
     response::{IntoResponse, Json, Response},
 };
 use serde_json::json;
 use std::sync::Arc;
 
-use crate::auth::jwt;
+use crate::audit::Actor;
+use crate::auth::error::AuthError;
+use crate::auth::jwt::TokenType;
+use crate::auth::{api_keys, jwt, revocation, scopes};
+use crate::error::AppError;
 use crate::AppState;
 
 #[derive(Debug, Clone)]
@@ -15,72 +19,329 @@ pub struct AuthUser {
     pub user_id: uuid::Uuid,
     pub username: String,
     pub role: String,
+    /// `jti` of the access token used for this request; the key id for API keys
+    pub token_id: String,
+    /// `exp` of the access token used for this request; for API keys the
+    /// key's expiry, or `i64::MAX` when it has none
+    pub token_exp: i64,
+    /// The access token itself, forwarded upstream when `UPSTREAM_AUTH=forward`;
+    /// empty for API keys
+    pub token: String,
+    /// Set when the request authenticated with `X-API-Key` instead of a JWT
+    pub api_key_id: Option<uuid::Uuid>,
+    /// What the request may do, checked by `require_scope`: the API key's
+    /// scopes, or the defaults of the user's role for JWTs
+    pub scopes: Vec<String>,
+    /// Set while the account has an admin-issued temporary password;
+    /// `require_password_changed` then turns the request away
+    pub must_change_password: bool,
 }
 
+impl AuthUser {
+    /// This user as the actor of an audit log entry
+    pub fn actor(&self) -> Actor<'_> {
+        Actor {
+            user_id: Some(self.user_id),
+            username: &self.username,
+        }
+    }
+}
+
+/// Authenticate the request with an `Authorization: Bearer` JWT or, failing
+/// that, an `X-API-Key` header.
 pub async fn auth_middleware(
     State(state): State<Arc<AppState>>,
     mut req: Request,
     next: Next,
 ) -> Response {
-    let auth_header = req
-        .headers()
-        .get("Authorization")
-        .and_then(|v| v.to_str().ok());
-
-    let token = match auth_header {
-        Some(h) if h.starts_with("Bearer ") => &h[7..],
-        _ => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(json!({
-                    "success": false,
-                    "error": {
-                        "code": "UNAUTHORIZED",
-                        "message": "Missing or invalid authorization header"
-                    }
-                })),
-            )
-                .into_response();
-        }
+    let authenticated = if let Some(token) = bearer_token(req.headers()) {
+        authenticate_token(&state, token.to_string()).await
+    } else if let Some(key) = api_keys::from_headers(req.headers()) {
+        authenticate_api_key(&state, key).await
+    } else {
+        return unauthorized("Missing or invalid authorization header");
     };
 
-    match jwt::verify_token(token, &state.config.jwt_secret) {
-        Ok(claims) => {
-            let user_id = match uuid::Uuid::parse_str(&claims.sub) {
-                Ok(id) => id,
-                Err(_) => {
-                    return (
-                        StatusCode::UNAUTHORIZED,
-                        Json(json!({
-                            "success": false,
-                            "error": {
-                                "code": "UNAUTHORIZED",
-                                "message": "Invalid token"
-                            }
-                        })),
-                    )
-                        .into_response();
-                }
-            };
-
-            let auth_user = AuthUser {
-                user_id,
-                username: claims.username,
-                role: claims.role,
-            };
+    match authenticated {
+        Ok(auth_user) => {
             req.extensions_mut().insert(auth_user);
             next.run(req).await
         }
-        Err(_) => (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Invalid or expired token"
-                }
-            })),
-        )
-            .into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn authenticate_token(state: &AppState, token: String) -> Result<AuthUser, AppError> {
+    let claims = jwt::verify_token(&token, &state.jwt_keys, TokenType::Access)?;
+    let user_id = uuid::Uuid::parse_str(&claims.sub).map_err(|_| AuthError::Invalid)?;
+
+    if revocation::is_access_token_revoked(
+        &state.redis,
+        &claims.jti,
+        user_id,
+        claims.issued_at_ms(),
+    )
+    .await?
+    {
+        return Err(AppError::Unauthorized);
+    }
+
+    // Tokens outlive deactivation and deletion of their account, and a
+    // password reset may have flagged it since the token was issued
+    let must_change_password: bool = sqlx::query_scalar(
+        "SELECT must_change_password FROM users WHERE id = $1 AND is_active = true",
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(AppError::Unauthorized)?;
+
+    Ok(AuthUser {
+        user_id,
+        scopes: scopes::for_role(&claims.role),
+        username: claims.username,
+        role: claims.role,
+        token_id: claims.jti,
+        token_exp: claims.exp,
+        token,
+        api_key_id: None,
+        must_change_password,
+    })
+}
+
+async fn authenticate_api_key(state: &AppState, key: &str) -> Result<AuthUser, AppError> {
+    let resolved = api_keys::resolve(&state.db, key)
+        .await?
+        .ok_or(AuthError::ApiKey)?;
+
+    Ok(AuthUser {
+        user_id: resolved.user_id,
+        username: resolved.username,
+        role: resolved.role,
+        token_id: resolved.key_id.to_string(),
+        token_exp: resolved
+            .expires_at
+            .map(|expires_at| expires_at.timestamp())
+            .unwrap_or(i64::MAX),
+        token: String::new(),
+        api_key_id: Some(resolved.key_id),
+        scopes: resolved.scopes,
+        must_change_password: resolved.must_change_password,
+    })
+}
+
+/// Token from an `Authorization: Bearer <token>` header.
+///
+/// The scheme is matched case-insensitively and surrounding whitespace is
+/// ignored; a missing, empty or whitespace-containing token yields `None`.
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?.trim();
+    let (scheme, token) = value.split_once(char::is_whitespace)?;
+    let token = token.trim();
+
+    if !scheme.eq_ignore_ascii_case("bearer")
+        || token.is_empty()
+        || token.contains(char::is_whitespace)
+    {
+        return None;
+    }
+    Some(token)
+}
+
+/// 401 in the standard error envelope, with a message specific to the failure.
+fn unauthorized(message: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(json!({
+            "success": false,
+            "data": null,
+            "error": {
+                "code": "UNAUTHORIZED",
+                "message": message,
+                "request_id": crate::request_id::current()
+            }
+        })),
+    )
+        .into_response()
+}
+
+/// Role guard layered after `auth_middleware`.
+///
+/// Rejects the request with `AppError::Forbidden` unless the authenticated
+/// user's role is one of `allowed`. Compose per route group with
+/// `middleware::from_fn(|req, next| require_role(&["admin"], req, next))`.
+pub async fn require_role(
+    allowed: &'static [&'static str],
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<AuthUser>()
+        .ok_or(AppError::Unauthorized)?;
+
+    if !allowed.contains(&auth_user.role.as_str()) {
+        tracing::warn!(
+            user = %auth_user.username,
+            role = %auth_user.role,
+            path = %req.uri().path(),
+            "Access denied: insufficient role"
+        );
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Scope guard layered after `auth_middleware`, alongside `require_role`.
+///
+/// Rejects the request with `AppError::Forbidden` unless the caller's scopes
+/// include `scope`. Compose per route group with
+/// `middleware::from_fn(|req, next| require_scope(scopes::CHAT_READ, req, next))`.
+pub async fn require_scope(
+    scope: &'static str,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<AuthUser>()
+        .ok_or(AppError::Unauthorized)?;
+
+    if !auth_user.scopes.iter().any(|s| s == scope) {
+        tracing::warn!(
+            user = %auth_user.username,
+            scope,
+            api_key_id = %auth_user.api_key_id.map(|id| id.to_string()).unwrap_or_default(),
+            path = %req.uri().path(),
+            "Access denied: missing scope"
+        );
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Guard layered after `auth_middleware` on everything but the caller's own
+/// account endpoints.
+///
+/// Rejects the request with `AppError::PasswordChangeRequired` while the
+/// account still has the temporary password an admin reset gave it, whether
+/// it signed in with a JWT or an API key.
+pub async fn require_password_changed(req: Request, next: Next) -> Result<Response, AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<AuthUser>()
+        .ok_or(AppError::Unauthorized)?;
+
+    if auth_user.must_change_password {
+        return Err(AppError::PasswordChangeRequired);
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, middleware, routing::get, Extension, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn auth_user(role: &str) -> AuthUser {
+        AuthUser {
+            user_id: uuid::Uuid::new_v4(),
+            username: "alice".to_string(),
+            role: role.to_string(),
+            token_id: "jti".to_string(),
+            token_exp: i64::MAX,
+            token: String::new(),
+            api_key_id: None,
+            scopes: scopes::for_role(role),
+            must_change_password: false,
+        }
+    }
+
+    /// Status of a request to a route limited to admins and editors
+    async fn status_as(auth_user: Option<AuthUser>) -> StatusCode {
+        let mut app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn(|req, next| {
+                require_role(&["admin", "editor"], req, next)
+            }));
+        if let Some(auth_user) = auth_user {
+            app = app.layer(Extension(auth_user));
+        }
+        app.oneshot(Request::new(Body::empty()))
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn allowed_roles_pass() {
+        assert_eq!(status_as(Some(auth_user("admin"))).await, StatusCode::OK);
+        assert_eq!(status_as(Some(auth_user("editor"))).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn other_roles_are_forbidden() {
+        assert_eq!(
+            status_as(Some(auth_user("user"))).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn unauthenticated_requests_are_unauthorized() {
+        assert_eq!(status_as(None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    fn authorization(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn bearer_scheme_is_case_insensitive() {
+        assert_eq!(bearer_token(&authorization("bearer abc")), Some("abc"));
+        assert_eq!(bearer_token(&authorization("BEARER abc")), Some("abc"));
+    }
+
+    #[test]
+    fn whitespace_around_the_token_is_ignored() {
+        assert_eq!(bearer_token(&authorization("Bearer   abc  ")), Some("abc"));
+        assert_eq!(bearer_token(&authorization("Bearer\tabc")), Some("abc"));
+    }
+
+    #[test]
+    fn empty_or_malformed_tokens_are_rejected() {
+        assert_eq!(bearer_token(&authorization("Bearer")), None);
+        assert_eq!(bearer_token(&authorization("Bearer    ")), None);
+        assert_eq!(bearer_token(&authorization("Bearer a b")), None);
+        assert_eq!(bearer_token(&authorization("Basic abc")), None);
+        assert_eq!(bearer_token(&HeaderMap::new()), None);
+    }
+
+    #[tokio::test]
+    async fn lowercase_scheme_authenticates_and_empty_token_gets_the_envelope() {
+        let app = crate::test_support::spawn(crate::test_support::config()).await;
+        let (_, token) = app.signed_in("user").await;
+        let me = |authorization: String| {
+            app.client
+                .get(app.url("/api/v1/auth/me"))
+                .header(header::AUTHORIZATION, authorization)
+                .send()
+        };
+
+        let response = me(format!("bearer {} ", token)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = me("Bearer ".to_string()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"]["code"], "UNAUTHORIZED");
     }
 }
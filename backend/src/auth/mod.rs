@@ -1,2 +1,11 @@
+pub mod api_keys;
+pub mod cookie;
+pub mod error;
 pub mod jwt;
+pub mod lockout;
 pub mod middleware;
+pub mod password;
+pub mod refresh_tokens;
+pub mod revocation;
+pub mod scopes;
+pub mod upstream;
@@ -0,0 +1,107 @@
+use rand::seq::IndexedRandom;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use crate::error::AppError;
+
+const MIN_PASSWORD_CHARS: usize = 10;
+
+/// Length of passwords made by `generate_temporary`
+const TEMPORARY_PASSWORD_CHARS: usize = 16;
+
+/// Characters of temporary passwords; look-alikes such as `0`/`O` and `1`/`l` are left out
+const TEMPORARY_PASSWORD_ALPHABET: &[u8] =
+    b"abcdefghijkmnpqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Hashes verified against when there is no account to check, one per cost
+static DUMMY_HASHES: LazyLock<Mutex<HashMap<u32, String>>> = LazyLock::new(Default::default);
+
+/// Require a minimum length and at least three of: lowercase, uppercase, digit, symbol.
+pub fn check_policy(password: &str) -> Result<(), AppError> {
+    if password.chars().count() < MIN_PASSWORD_CHARS {
+        return Err(AppError::Validation(format!(
+            "password must be at least {} characters",
+            MIN_PASSWORD_CHARS
+        )));
+    }
+
+    let classes = [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_ascii_digit()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ];
+    if classes.iter().filter(|present| **present).count() < 3 {
+        return Err(AppError::Validation(
+            "password must contain at least three of: lowercase, uppercase, digits, symbols"
+                .to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn hash(password: &str, cost: u32) -> Result<String, AppError> {
+    bcrypt::hash(password, cost)
+        .map_err(|e| AppError::Internal(format!("Password hashing failed: {}", e)))
+}
+
+/// Check `password` against a hash of nothing at `cost`, always failing.
+///
+/// Takes as long as checking a real account's password, so a login for an
+/// unknown or inactive username can't be told apart by its response time.
+pub fn verify_without_account(password: &str, cost: u32) -> Result<bool, AppError> {
+    let dummy = {
+        let mut hashes = DUMMY_HASHES.lock().unwrap();
+        match hashes.get(&cost) {
+            Some(dummy) => dummy.clone(),
+            None => {
+                let dummy = hash(&generate_temporary(), cost)?;
+                hashes.insert(cost, dummy.clone());
+                dummy
+            }
+        }
+    };
+    bcrypt::verify(password, &dummy)
+        .map_err(|_| AppError::Internal("Password verification failed".to_string()))?;
+    Ok(false)
+}
+
+/// Whether `password_hash` was made with a lower cost than `cost` and should be
+/// replaced; unparseable hashes are left alone.
+pub fn needs_rehash(password_hash: &str, cost: u32) -> bool {
+    password_hash
+        .parse::<bcrypt::HashParts>()
+        .map(|parts| parts.get_cost() < cost)
+        .unwrap_or(false)
+}
+
+/// A random password meeting `check_policy`, for an admin to hand to a user.
+pub fn generate_temporary() -> String {
+    let mut rng = rand::rng();
+    loop {
+        let password: String = (0..TEMPORARY_PASSWORD_CHARS)
+            .filter_map(|_| TEMPORARY_PASSWORD_ALPHABET.choose(&mut rng))
+            .map(|c| *c as char)
+            .collect();
+        if check_policy(&password).is_ok() {
+            return password;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_without_an_account_does_a_full_verification_and_fails() {
+        assert!(!verify_without_account("Test-password-1", 4).unwrap());
+        let dummy = DUMMY_HASHES.lock().unwrap()[&4].clone();
+        let parts: bcrypt::HashParts = dummy.parse().unwrap();
+        assert_eq!(parts.get_cost(), 4);
+        // Reused rather than hashed again on every login
+        assert!(!verify_without_account("other", 4).unwrap());
+        assert_eq!(DUMMY_HASHES.lock().unwrap()[&4], dummy);
+    }
+}
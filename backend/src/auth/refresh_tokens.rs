@@ -0,0 +1,100 @@
+use chrono::DateTime;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::auth::jwt::Claims;
+use crate::error::AppError;
+
+fn parse_jti(claims: &Claims) -> Result<Uuid, AppError> {
+    Uuid::parse_str(&claims.jti).map_err(|_| AppError::Unauthorized)
+}
+
+/// Record a newly issued refresh token so it can be rotated exactly once.
+pub async fn store(
+    db: impl PgExecutor<'_>,
+    user_id: Uuid,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    let expires_at = DateTime::from_timestamp(claims.exp, 0)
+        .ok_or_else(|| AppError::Internal("Invalid refresh token expiry".to_string()))?;
+
+    sqlx::query("INSERT INTO refresh_tokens (jti, user_id, expires_at) VALUES ($1, $2, $3)")
+        .bind(parse_jti(claims)?)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Mark the presented refresh token as used, rejecting it when it is unknown,
+/// expired or already used.
+///
+/// After a rejection call `revoke_if_reused` outside the failed transaction.
+pub async fn rotate(
+    db: impl PgExecutor<'_>,
+    user_id: Uuid,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    let jti = parse_jti(claims)?;
+
+    let rotated = sqlx::query(
+        "UPDATE refresh_tokens SET revoked = true \
+         WHERE jti = $1 AND user_id = $2 AND revoked = false AND expires_at > NOW()",
+    )
+    .bind(jti)
+    .bind(user_id)
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    if rotated == 1 {
+        return Ok(());
+    }
+
+    Err(AppError::Unauthorized)
+}
+
+/// Handle a refresh token that `rotate` rejected.
+///
+/// A token that is already revoked indicates replay of a stolen token, so
+/// every refresh token of that user is revoked.
+pub async fn revoke_if_reused(db: &PgPool, user_id: Uuid, claims: &Claims) -> Result<(), AppError> {
+    let jti = parse_jti(claims)?;
+
+    let reused: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM refresh_tokens WHERE jti = $1 AND user_id = $2 AND revoked = true)",
+    )
+    .bind(jti)
+    .bind(user_id)
+    .fetch_one(db)
+    .await?;
+
+    if reused {
+        let revoked = revoke_all_for_user(db, user_id).await?;
+        tracing::warn!(
+            user_id = %user_id,
+            jti = %jti,
+            revoked,
+            "Refresh token reuse detected, revoked all refresh tokens for user"
+        );
+    }
+
+    Ok(())
+}
+
+/// Revoke every outstanding refresh token of a user, returning how many were revoked.
+///
+/// Expired tokens are already unusable and not counted.
+pub async fn revoke_all_for_user(db: impl PgExecutor<'_>, user_id: Uuid) -> Result<u64, AppError> {
+    let result = sqlx::query(
+        "UPDATE refresh_tokens SET revoked = true \
+         WHERE user_id = $1 AND revoked = false AND expires_at > NOW()",
+    )
+    .bind(user_id)
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected())
+}
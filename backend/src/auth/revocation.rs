@@ -0,0 +1,71 @@
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+const KEY_PREFIX: &str = "auth:revoked:";
+const USER_KEY_PREFIX: &str = "auth:revoked_before_ms:";
+
+fn key(jti: &str) -> String {
+    format!("{}{}", KEY_PREFIX, jti)
+}
+
+fn user_key(user_id: Uuid) -> String {
+    format!("{}{}", USER_KEY_PREFIX, user_id)
+}
+
+/// Add a token's `jti` to the denylist until the token would have expired anyway.
+pub async fn revoke(
+    redis: &ConnectionManager,
+    jti: &str,
+    exp: i64,
+) -> Result<(), redis::RedisError> {
+    let remaining = exp - Utc::now().timestamp();
+    if remaining <= 0 {
+        return Ok(());
+    }
+
+    let mut conn = redis.clone();
+    conn.set_ex::<_, _, ()>(key(jti), 1, remaining as u64).await
+}
+
+pub async fn is_revoked(redis: &ConnectionManager, jti: &str) -> Result<bool, redis::RedisError> {
+    let mut conn = redis.clone();
+    conn.exists(key(jti)).await
+}
+
+/// Revoke every token of a user issued up to now, for `ttl_secs` (the longest
+/// lifetime of an outstanding token).
+///
+/// Access token `jti`s aren't tracked, so this stores an issued-at cutoff,
+/// in milliseconds, instead of denylisting them one by one.
+pub async fn revoke_all_before_now(
+    redis: &ConnectionManager,
+    user_id: Uuid,
+    ttl_secs: u64,
+) -> Result<(), redis::RedisError> {
+    let mut conn = redis.clone();
+    conn.set_ex::<_, _, ()>(user_key(user_id), Utc::now().timestamp_millis(), ttl_secs)
+        .await
+}
+
+/// Whether an access token is denylisted by `jti` or issued before its user's cutoff.
+///
+/// The cutoff is compared in milliseconds (`issued_at_ms`), so every token
+/// issued before it is revoked, including ones from the same second, while
+/// signing in again right after it isn't turned away.
+pub async fn is_access_token_revoked(
+    redis: &ConnectionManager,
+    jti: &str,
+    user_id: Uuid,
+    issued_at_ms: i64,
+) -> Result<bool, redis::RedisError> {
+    let mut conn = redis.clone();
+    let (denylisted, revoked_before): (bool, Option<i64>) = redis::pipe()
+        .exists(key(jti))
+        .get(user_key(user_id))
+        .query_async(&mut conn)
+        .await?;
+
+    Ok(denylisted || revoked_before.is_some_and(|cutoff| issued_at_ms <= cutoff))
+}
@@ -0,0 +1,48 @@
+use crate::error::AppError;
+
+/// Ask questions: chat streams, conversation history and search
+pub const CHAT_READ: &str = "chat:read";
+/// List documents and read their processing status
+pub const DOCUMENTS_READ: &str = "documents:read";
+/// Upload, reprocess and delete documents
+pub const DOCUMENTS_WRITE: &str = "documents:write";
+/// Manage user accounts and their API keys
+pub const ADMIN_USERS: &str = "admin:users";
+/// Reload the query blocklist
+pub const ADMIN_MODERATION: &str = "admin:moderation";
+/// Read the gateway's runtime statistics
+pub const ADMIN_STATS: &str = "admin:stats";
+
+pub const ALL: &[&str] = &[
+    CHAT_READ,
+    DOCUMENTS_READ,
+    DOCUMENTS_WRITE,
+    ADMIN_USERS,
+    ADMIN_MODERATION,
+    ADMIN_STATS,
+];
+
+/// Scopes of a JWT user, and of API keys created without explicit scopes.
+///
+/// Scopes narrow what a role allows rather than extend it: routes check the
+/// role as well, so a key can't do more than its owner.
+pub fn for_role(role: &str) -> Vec<String> {
+    let scopes: &[&str] = match role {
+        "admin" => ALL,
+        "editor" => &[CHAT_READ, DOCUMENTS_READ, DOCUMENTS_WRITE],
+        _ => &[CHAT_READ, DOCUMENTS_READ],
+    };
+    scopes.iter().map(|s| s.to_string()).collect()
+}
+
+/// Reject scopes that no route checks, which are most likely typos.
+pub fn check_known(scopes: &[String]) -> Result<(), AppError> {
+    match scopes.iter().find(|s| !ALL.contains(&s.as_str())) {
+        Some(unknown) => Err(AppError::Validation(format!(
+            "Unknown scope {:?}; expected one of: {}",
+            unknown,
+            ALL.join(", ")
+        ))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,154 @@
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+
+use crate::auth::jwt;
+use crate::auth::middleware::AuthUser;
+use crate::config::UpstreamAuth;
+use crate::error::AppError;
+use crate::AppState;
+
+pub static USER_ID_HEADER: HeaderName = HeaderName::from_static("x-user-id");
+
+/// Headers identifying `user` to the ETL / LLM services, per `UPSTREAM_AUTH`.
+///
+/// `forward` passes on the caller's own access token; `service_token` mints a
+/// fresh one that expires after `upstream_token_ttl_secs`, as `forward` does
+/// for callers using an API key. Both modes also
+/// send `X-User-Id`. `none` adds nothing.
+pub fn identity_headers(state: &AppState, user: &AuthUser) -> Result<HeaderMap, AppError> {
+    let mut headers = HeaderMap::new();
+
+    let token = match state.config.upstream_auth {
+        UpstreamAuth::None => return Ok(headers),
+        UpstreamAuth::Forward if user.api_key_id.is_none() => user.token.clone(),
+        // An API key isn't a JWT the upstream services could verify, so one is minted
+        UpstreamAuth::Forward | UpstreamAuth::ServiceToken => jwt::create_access_token(
+            user.user_id,
+            &user.username,
+            &user.role,
+            &state.jwt_keys,
+            state.config.upstream_token_ttl_secs,
+        )?,
+    };
+
+    let authorization = HeaderValue::from_str(&format!("Bearer {}", token))
+        .map_err(|_| AppError::Internal("Invalid upstream authorization header".to_string()))?;
+    headers.insert(header::AUTHORIZATION, authorization);
+    headers.insert(
+        USER_ID_HEADER.clone(),
+        HeaderValue::from_str(&user.user_id.to_string()).expect("UUID is a valid header value"),
+    );
+
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        routing::{get, post},
+        Json, Router,
+    };
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::auth::jwt::TokenType;
+    use crate::test_support::{self, TestUser};
+
+    /// Headers of the ETL document list and LLM chat calls made for one user
+    async fn upstream_headers(mode: UpstreamAuth) -> (TestUser, String, Vec<HeaderMap>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let upstream = Router::new()
+            .route(
+                "/api/v1/documents",
+                get({
+                    let received = received.clone();
+                    move |headers: HeaderMap| async move {
+                        received.lock().unwrap().push(headers);
+                        Json(json!({ "success": true, "data": [] }))
+                    }
+                }),
+            )
+            .route(
+                "/api/v1/chat/stream",
+                post({
+                    let received = received.clone();
+                    move |headers: HeaderMap| async move {
+                        received.lock().unwrap().push(headers);
+                        (
+                            [(header::CONTENT_TYPE, "text/event-stream")],
+                            "data: {\"content\":\"Hi\"}\n\n",
+                        )
+                    }
+                }),
+            );
+        let url = test_support::mock_service(upstream).await;
+        let mut config = test_support::config();
+        config.etl_service_url = url.clone();
+        config.llm_service_url = url;
+        config.upstream_auth = mode;
+        let app = test_support::spawn(config).await;
+        let (user, token) = app.signed_in("editor").await;
+
+        let documents = app
+            .client
+            .get(app.url("/api/v1/documents"))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert!(documents.status().is_success());
+        let chat = app
+            .client
+            .post(app.url("/api/v1/chat/stream"))
+            .bearer_auth(&token)
+            .json(&json!({ "query": "hello", "use_context": false }))
+            .send()
+            .await
+            .unwrap();
+        assert!(chat.status().is_success());
+        chat.text().await.unwrap();
+
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 2);
+        (user, token, received)
+    }
+
+    fn bearer(headers: &HeaderMap) -> &str {
+        headers[header::AUTHORIZATION]
+            .to_str()
+            .unwrap()
+            .strip_prefix("Bearer ")
+            .expect("bearer token")
+    }
+
+    #[tokio::test]
+    async fn none_sends_no_identity() {
+        let (_, _, received) = upstream_headers(UpstreamAuth::None).await;
+        for headers in &received {
+            assert!(headers.get(header::AUTHORIZATION).is_none());
+            assert!(headers.get(&USER_ID_HEADER).is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn forward_passes_on_the_callers_token() {
+        let (user, token, received) = upstream_headers(UpstreamAuth::Forward).await;
+        for headers in &received {
+            assert_eq!(bearer(headers), token);
+            assert_eq!(headers[&USER_ID_HEADER], user.id.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn service_token_mints_a_token_for_the_caller() {
+        let (user, token, received) = upstream_headers(UpstreamAuth::ServiceToken).await;
+        let keys = jwt::JwtKeys::from_config(&test_support::config()).unwrap();
+        for headers in &received {
+            let minted = bearer(headers);
+            assert_ne!(minted, token);
+            let claims = jwt::verify_token(minted, &keys, TokenType::Access).unwrap();
+            assert_eq!(claims.sub, user.id.to_string());
+            assert_eq!(headers[&USER_ID_HEADER], user.id.to_string());
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::error::AppError;
+
+/// Per-item outcome of a batch operation.
+///
+/// Items are identified by their position in the request. Succeeded items go
+/// to `results` and failed ones to `errors`, each with the code and message
+/// the item would have failed with on its own. Any failure makes the
+/// response `207 Multi-Status`; a batch where every item succeeded is `200`.
+#[derive(Debug, Serialize)]
+pub struct BatchResponse<T> {
+    pub results: Vec<BatchResult<T>>,
+    pub errors: Vec<BatchError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchResult<T> {
+    pub index: usize,
+    pub data: T,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchError {
+    pub index: usize,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl<T> BatchResponse<T> {
+    pub fn new() -> Self {
+        Self {
+            results: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Record the outcome of the item at `index`.
+    pub fn push(&mut self, index: usize, result: Result<T, AppError>) {
+        match result {
+            Ok(data) => self.results.push(BatchResult { index, data }),
+            Err(e) => {
+                let (_, code, message) = e.parts();
+                self.errors.push(BatchError {
+                    index,
+                    code,
+                    message,
+                });
+            }
+        }
+    }
+
+    /// Number of items recorded so far
+    pub fn len(&self) -> usize {
+        self.results.len() + self.errors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Default for BatchResponse<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Serialize> IntoResponse for BatchResponse<T> {
+    fn into_response(self) -> Response {
+        let status = if self.errors.is_empty() {
+            StatusCode::OK
+        } else {
+            StatusCode::MULTI_STATUS
+        };
+        (status, Json(json!({ "success": true, "data": self }))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    async fn respond(batch: BatchResponse<&'static str>) -> (StatusCode, Value) {
+        let response = batch.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn failed_item_makes_the_batch_multi_status() {
+        let mut batch = BatchResponse::new();
+        batch.push(0, Ok("first"));
+        batch.push(1, Err(AppError::NotFound("Document not found".to_string())));
+        batch.push(2, Ok("third"));
+        assert_eq!(batch.len(), 3);
+
+        let (status, body) = respond(batch).await;
+        assert_eq!(status, StatusCode::MULTI_STATUS);
+        assert_eq!(
+            body,
+            json!({
+                "success": true,
+                "data": {
+                    "results": [
+                        { "index": 0, "data": "first" },
+                        { "index": 2, "data": "third" },
+                    ],
+                    "errors": [
+                        { "index": 1, "code": "NOT_FOUND", "message": "Document not found" },
+                    ],
+                },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_without_failures_is_ok() {
+        let mut batch = BatchResponse::new();
+        batch.push(0, Ok("only"));
+
+        let (status, body) = respond(batch).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["errors"], json!([]));
+    }
+}
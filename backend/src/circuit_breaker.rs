@@ -0,0 +1,215 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Fails calls to an unhealthy upstream fast instead of waiting on each one.
+///
+/// Opens after `failure_threshold` consecutive failures within `failure_window`.
+/// While open, `try_acquire` refuses calls until `cooldown` has passed; then a
+/// single probe is let through (half-open) and its outcome closes or reopens
+/// the breaker. A `failure_threshold` of 0 disables the breaker.
+pub struct CircuitBreaker {
+    name: &'static str,
+    failure_threshold: u32,
+    failure_window: Duration,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+enum BreakerState {
+    Closed {
+        failures: u32,
+        first_failure_at: Option<Instant>,
+    },
+    Open {
+        opened_at: Instant,
+    },
+    /// A probe has been let through and has not reported back yet
+    HalfOpen {
+        probe_started_at: Instant,
+    },
+}
+
+impl CircuitBreaker {
+    pub fn new(
+        name: &'static str,
+        failure_threshold: u32,
+        failure_window: Duration,
+        cooldown: Duration,
+    ) -> Self {
+        Self {
+            name,
+            failure_threshold,
+            failure_window,
+            cooldown,
+            state: Mutex::new(BreakerState::Closed {
+                failures: 0,
+                first_failure_at: None,
+            }),
+        }
+    }
+
+    /// Whether a call may be attempted now.
+    ///
+    /// A probe that never reports back, e.g. because its client disconnected,
+    /// is given up on after `cooldown` and another probe is allowed.
+    pub fn try_acquire(&self) -> bool {
+        if self.failure_threshold == 0 {
+            return true;
+        }
+
+        let mut state = self.lock();
+        match *state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { opened_at } if opened_at.elapsed() >= self.cooldown => {
+                tracing::info!(upstream = self.name, "Circuit breaker half-open, probing");
+                *state = BreakerState::HalfOpen {
+                    probe_started_at: Instant::now(),
+                };
+                true
+            }
+            BreakerState::HalfOpen { probe_started_at }
+                if probe_started_at.elapsed() >= self.cooldown =>
+            {
+                *state = BreakerState::HalfOpen {
+                    probe_started_at: Instant::now(),
+                };
+                true
+            }
+            BreakerState::Open { .. } | BreakerState::HalfOpen { .. } => false,
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.lock();
+        if !matches!(*state, BreakerState::Closed { failures: 0, .. }) {
+            if matches!(*state, BreakerState::HalfOpen { .. }) {
+                tracing::info!(upstream = self.name, "Circuit breaker closed");
+            }
+            *state = BreakerState::Closed {
+                failures: 0,
+                first_failure_at: None,
+            };
+        }
+    }
+
+    pub fn record_failure(&self) {
+        if self.failure_threshold == 0 {
+            return;
+        }
+
+        let mut state = self.lock();
+        let now = Instant::now();
+        let (failures, first_failure_at) = match *state {
+            BreakerState::Closed {
+                failures,
+                first_failure_at: Some(first),
+            } if now.duration_since(first) < self.failure_window => (failures + 1, first),
+            BreakerState::Closed { .. } => (1, now),
+            BreakerState::HalfOpen { .. } => {
+                tracing::warn!(
+                    upstream = self.name,
+                    "Circuit breaker probe failed, reopening"
+                );
+                *state = BreakerState::Open { opened_at: now };
+                return;
+            }
+            // A call let through before the breaker opened
+            BreakerState::Open { .. } => return,
+        };
+
+        if failures >= self.failure_threshold {
+            tracing::warn!(
+                upstream = self.name,
+                failures,
+                cooldown_secs = self.cooldown.as_secs(),
+                "Circuit breaker opened"
+            );
+            *state = BreakerState::Open { opened_at: now };
+        } else {
+            *state = BreakerState::Closed {
+                failures,
+                first_failure_at: Some(first_failure_at),
+            };
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_millis(50);
+
+    fn breaker(failure_threshold: u32) -> CircuitBreaker {
+        CircuitBreaker::new("test", failure_threshold, Duration::from_secs(60), COOLDOWN)
+    }
+
+    #[test]
+    fn opens_after_the_threshold_and_fails_fast() {
+        let breaker = breaker(3);
+        for _ in 0..2 {
+            breaker.record_failure();
+            assert!(breaker.try_acquire());
+        }
+        breaker.record_failure();
+        assert!(!breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let breaker = breaker(2);
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        assert!(breaker.try_acquire());
+    }
+
+    #[test]
+    fn failures_outside_the_window_do_not_add_up() {
+        let breaker = CircuitBreaker::new("test", 2, Duration::from_millis(20), COOLDOWN);
+        breaker.record_failure();
+        std::thread::sleep(Duration::from_millis(30));
+        breaker.record_failure();
+        assert!(breaker.try_acquire());
+    }
+
+    #[test]
+    fn successful_probe_after_the_cooldown_closes() {
+        let breaker = breaker(1);
+        breaker.record_failure();
+        assert!(!breaker.try_acquire());
+
+        std::thread::sleep(COOLDOWN);
+        assert!(breaker.try_acquire());
+        // Only one probe at a time
+        assert!(!breaker.try_acquire());
+        breaker.record_success();
+        assert!(breaker.try_acquire());
+        assert!(breaker.try_acquire());
+    }
+
+    #[test]
+    fn failed_probe_reopens() {
+        let breaker = breaker(1);
+        breaker.record_failure();
+        std::thread::sleep(COOLDOWN);
+        assert!(breaker.try_acquire());
+
+        breaker.record_failure();
+        assert!(!breaker.try_acquire());
+    }
+
+    #[test]
+    fn zero_threshold_never_opens() {
+        let breaker = breaker(0);
+        for _ in 0..10 {
+            breaker.record_failure();
+        }
+        assert!(breaker.try_acquire());
+    }
+}
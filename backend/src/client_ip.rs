@@ -0,0 +1,151 @@
+use axum::{
+    extract::{connect_info::Connected, ConnectInfo},
+    http::{request::Parts, HeaderMap},
+    serve::IncomingStream,
+};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::shutdown::TrackedListener;
+
+/// Remote address of a connection, stored in request extensions as `ConnectInfo<PeerAddr>`
+#[derive(Debug, Clone, Copy)]
+pub struct PeerAddr(pub SocketAddr);
+
+impl Connected<IncomingStream<'_, TrackedListener>> for PeerAddr {
+    fn connect_info(stream: IncomingStream<'_, TrackedListener>) -> Self {
+        PeerAddr(*stream.remote_addr())
+    }
+}
+
+/// Address of the client that sent a request.
+///
+/// `None` only when the connection's peer address is unknown, which doesn't
+/// happen when serving with `into_make_service_with_connect_info`.
+pub fn from_parts(parts: &Parts, trusted_proxies: &[IpNet]) -> Option<IpAddr> {
+    let ConnectInfo(PeerAddr(peer)) = parts.extensions.get::<ConnectInfo<PeerAddr>>()?;
+    Some(resolve(peer.ip(), &parts.headers, trusted_proxies))
+}
+
+/// The peer address, unless the peer is a trusted proxy.
+///
+/// Behind trusted proxies `X-Forwarded-For` is walked from the right, since
+/// each proxy appends the address it received the request from; the first
+/// hop that isn't itself a trusted proxy is the client. Anything left of it
+/// was supplied by the client and may be spoofed. `X-Real-IP` is used when
+/// `X-Forwarded-For` is absent.
+pub fn resolve(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+    if !is_trusted(&peer) {
+        return peer;
+    }
+
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect();
+
+    if forwarded.is_empty() {
+        return headers
+            .get("x-real-ip")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(peer);
+    }
+
+    let mut client = peer;
+    for hop in forwarded.iter().rev() {
+        // A malformed entry can't be attributed; stop at the last hop we trust
+        let Ok(ip) = hop.parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !is_trusted(&ip) {
+            break;
+        }
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxies() -> Vec<IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap()]
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn forwarded_for_is_ignored_from_untrusted_peers() {
+        let spoofed = headers(&[("x-forwarded-for", "1.2.3.4"), ("x-real-ip", "1.2.3.4")]);
+        assert_eq!(
+            resolve(ip("203.0.113.7"), &spoofed, &proxies()),
+            ip("203.0.113.7")
+        );
+        // Nor is any peer trusted without a configured list
+        assert_eq!(resolve(ip("10.0.0.2"), &spoofed, &[]), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn client_is_the_first_untrusted_hop_from_the_right() {
+        // The client prepended a bogus entry; the proxies appended the real hops
+        let forwarded = headers(&[("x-forwarded-for", "1.2.3.4, 198.51.100.9, 10.0.0.3")]);
+        assert_eq!(
+            resolve(ip("10.0.0.2"), &forwarded, &proxies()),
+            ip("198.51.100.9")
+        );
+    }
+
+    #[test]
+    fn forwarded_for_across_several_headers_is_one_list() {
+        let forwarded = headers(&[
+            ("x-forwarded-for", "198.51.100.9"),
+            ("x-forwarded-for", "10.0.0.3"),
+        ]);
+        assert_eq!(
+            resolve(ip("10.0.0.2"), &forwarded, &proxies()),
+            ip("198.51.100.9")
+        );
+    }
+
+    #[test]
+    fn malformed_hop_stops_at_the_last_trusted_one() {
+        let forwarded = headers(&[("x-forwarded-for", "198.51.100.9, garbage, 10.0.0.3")]);
+        assert_eq!(
+            resolve(ip("10.0.0.2"), &forwarded, &proxies()),
+            ip("10.0.0.3")
+        );
+    }
+
+    #[test]
+    fn real_ip_is_used_without_forwarded_for() {
+        let real_ip = headers(&[("x-real-ip", "198.51.100.9")]);
+        assert_eq!(
+            resolve(ip("10.0.0.2"), &real_ip, &proxies()),
+            ip("198.51.100.9")
+        );
+    }
+
+    #[test]
+    fn trusted_peer_without_headers_is_the_client() {
+        assert_eq!(
+            resolve(ip("10.0.0.2"), &HeaderMap::new(), &proxies()),
+            ip("10.0.0.2")
+        );
+    }
+}
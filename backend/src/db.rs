@@ -0,0 +1,108 @@
+use futures_util::future::BoxFuture;
+use sqlx::{PgConnection, PgPool};
+
+use crate::error::AppError;
+
+/// Run `f` in a transaction, committing when it returns `Ok` and rolling
+/// back when it returns `Err`.
+///
+/// Queries that must succeed or fail together run on the connection passed
+/// to `f` rather than on the pool. The future `f` returns may only borrow
+/// that connection, so anything else it needs is moved in.
+pub async fn transaction<T, F>(db: &PgPool, f: F) -> Result<T, AppError>
+where
+    F: for<'c> FnOnce(&'c mut PgConnection) -> BoxFuture<'c, Result<T, AppError>>,
+{
+    let mut tx = db.begin().await?;
+    match f(&mut tx).await {
+        Ok(value) => {
+            tx.commit().await?;
+            Ok(value)
+        }
+        Err(e) => {
+            if let Err(rollback_err) = tx.rollback().await {
+                // The connection is discarded, which rolls back on the server anyway
+                tracing::warn!("Failed to roll back transaction: {}", rollback_err);
+            }
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::test_support;
+
+    async fn display_name(db: &PgPool, id: Uuid) -> Option<String> {
+        sqlx::query_scalar("SELECT display_name FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    fn rename(conn: &mut PgConnection, id: Uuid) -> BoxFuture<'_, Result<(), AppError>> {
+        Box::pin(async move {
+            sqlx::query("UPDATE users SET display_name = 'Renamed' WHERE id = $1")
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+            Ok(())
+        })
+    }
+
+    #[tokio::test]
+    async fn ok_commits_every_query() {
+        let app = test_support::spawn(test_support::config()).await;
+        let user = app.user("user").await;
+
+        transaction(&app.state.db, move |conn| rename(conn, user.id))
+            .await
+            .unwrap();
+        assert_eq!(
+            display_name(&app.state.db, user.id).await.as_deref(),
+            Some("Renamed")
+        );
+    }
+
+    #[tokio::test]
+    async fn error_after_a_write_rolls_it_back() {
+        let app = test_support::spawn(test_support::config()).await;
+        let user = app.user("user").await;
+
+        let result: Result<(), AppError> = transaction(&app.state.db, move |conn| {
+            Box::pin(async move {
+                rename(conn, user.id).await?;
+                Err(AppError::Internal("forced".into()))
+            })
+        })
+        .await;
+        assert!(matches!(result, Err(AppError::Internal(_))));
+        assert_eq!(display_name(&app.state.db, user.id).await, None);
+    }
+
+    #[tokio::test]
+    async fn failing_query_rolls_back_earlier_writes() {
+        let app = test_support::spawn(test_support::config()).await;
+        let user = app.user("user").await;
+        let taken = app.user("user").await.username;
+
+        let result = transaction(&app.state.db, move |conn| {
+            Box::pin(async move {
+                rename(conn, user.id).await?;
+                sqlx::query("UPDATE users SET username = $1 WHERE id = $2")
+                    .bind(taken)
+                    .bind(user.id)
+                    .execute(&mut *conn)
+                    .await?;
+                Ok(())
+            })
+        })
+        .await;
+        assert!(matches!(result, Err(AppError::Database(_))));
+        assert_eq!(display_name(&app.state.db, user.id).await, None);
+    }
+}
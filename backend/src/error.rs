@@ -1,9 +1,18 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Json},
 };
+use chrono::{DateTime, Utc};
 use serde_json::json;
 
+use crate::auth::error::AuthError;
+
+/// `Retry-After` sent with `ServiceUnavailable`; upstream restarts usually take a few seconds
+const SERVICE_UNAVAILABLE_RETRY_AFTER_SECS: u64 = 5;
+
+/// Wait assumed when an upstream 429 has no usable `Retry-After`
+const UPSTREAM_RATE_LIMIT_RETRY_AFTER_SECS: u64 = 5;
+
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("Validation error: {0}")]
@@ -12,22 +21,54 @@ pub enum AppError {
     #[error("Unauthorized")]
     Unauthorized,
 
+    /// A presented JWT was rejected; the message says why
+    #[error("Invalid token: {0}")]
+    InvalidToken(AuthError),
+
     #[error("Forbidden")]
     Forbidden,
 
+    /// The account has an unchanged temporary password; only the caller's own
+    /// account endpoints are open until it is changed
+    #[error("Password change required")]
+    PasswordChangeRequired,
+
+    #[error("Account locked for {retry_after_secs}s")]
+    AccountLocked { retry_after_secs: u64 },
+
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Rate limited, retry after {retry_after_secs}s")]
+    RateLimited { retry_after_secs: u64 },
+
+    /// An upstream service (ETL / LLM) could not be reached
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    /// The request did not complete within `request_timeout_secs`
+    #[error("Request timed out")]
+    GatewayTimeout,
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> axum::response::Response {
-        let (status, code, message) = match &self {
+impl AppError {
+    /// HTTP status, machine-readable code and client-facing message.
+    ///
+    /// Internal details are logged here and replaced with a generic message.
+    pub fn parts(&self) -> (StatusCode, &'static str, String) {
+        match self {
             AppError::Validation(msg) => {
                 (StatusCode::BAD_REQUEST, "VALIDATION_ERROR", msg.clone())
             }
@@ -36,12 +77,42 @@ impl IntoResponse for AppError {
                 "UNAUTHORIZED",
                 "Authentication required".to_string(),
             ),
+            AppError::InvalidToken(e) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED", e.to_string()),
             AppError::Forbidden => (
                 StatusCode::FORBIDDEN,
                 "FORBIDDEN",
                 "Insufficient permissions".to_string(),
             ),
+            AppError::PasswordChangeRequired => (
+                StatusCode::FORBIDDEN,
+                "PASSWORD_CHANGE_REQUIRED",
+                "Change your password with POST /auth/me/password to continue".to_string(),
+            ),
+            AppError::AccountLocked { retry_after_secs } => (
+                StatusCode::FORBIDDEN,
+                "ACCOUNT_LOCKED",
+                format!(
+                    "Account temporarily locked due to repeated failed logins, retry after {} seconds",
+                    retry_after_secs
+                ),
+            ),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg.clone()),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, "CONFLICT", msg.clone()),
+            AppError::RateLimited { retry_after_secs } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "RATE_LIMITED",
+                format!("Too many requests, retry after {} seconds", retry_after_secs),
+            ),
+            AppError::ServiceUnavailable(msg) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "SERVICE_UNAVAILABLE",
+                msg.clone(),
+            ),
+            AppError::GatewayTimeout => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "GATEWAY_TIMEOUT",
+                "Request timed out".to_string(),
+            ),
             AppError::Database(e) => {
                 tracing::error!("Database error: {:?}", e);
                 (
@@ -50,6 +121,14 @@ impl IntoResponse for AppError {
                     "Internal server error".to_string(),
                 )
             }
+            AppError::Redis(e) => {
+                tracing::error!("Redis error: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "INTERNAL_ERROR",
+                    "Internal server error".to_string(),
+                )
+            }
             AppError::Internal(msg) => {
                 tracing::error!("Internal error: {}", msg);
                 (
@@ -58,17 +137,116 @@ impl IntoResponse for AppError {
                     "Internal server error".to_string(),
                 )
             }
-        };
+        }
+    }
+}
+
+impl AppError {
+    /// `RateLimited` for a 429 from the ETL or LLM service, passing on how
+    /// long the service asked callers to wait.
+    pub fn upstream_rate_limited(headers: &HeaderMap) -> Self {
+        AppError::RateLimited {
+            retry_after_secs: upstream_retry_after_secs(headers),
+        }
+    }
+}
+
+/// Seconds to wait according to an upstream `Retry-After`, given either as
+/// delta-seconds or as an HTTP date.
+pub fn upstream_retry_after_secs(headers: &HeaderMap) -> u64 {
+    let Some(value) = headers
+        .get(header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+    else {
+        return UPSTREAM_RATE_LIMIT_RETRY_AFTER_SECS;
+    };
+
+    if let Ok(secs) = value.parse() {
+        return secs;
+    }
+    match DateTime::parse_from_rfc2822(value) {
+        Ok(at) => (at.with_timezone(&Utc) - Utc::now()).num_seconds().max(0) as u64,
+        Err(_) => UPSTREAM_RATE_LIMIT_RETRY_AFTER_SECS,
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> axum::response::Response {
+        let (status, code, message) = self.parts();
 
         let body = json!({
             "success": false,
             "data": null,
             "error": {
                 "code": code,
-                "message": message
+                "message": message,
+                "request_id": crate::request_id::current()
             }
         });
 
-        (status, Json(body)).into_response()
+        let retry_after_secs = match self {
+            AppError::RateLimited { retry_after_secs } => Some(retry_after_secs),
+            AppError::ServiceUnavailable(_) => Some(SERVICE_UNAVAILABLE_RETRY_AFTER_SECS),
+            _ => None,
+        };
+
+        let mut response = (status, Json(body)).into_response();
+        if let Some(retry_after_secs) = retry_after_secs {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rate_limited_sets_status_and_retry_after() {
+        let response = AppError::RateLimited {
+            retry_after_secs: 42,
+        }
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "42");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "RATE_LIMITED");
+    }
+
+    #[tokio::test]
+    async fn service_unavailable_sets_status_code_and_retry_after() {
+        let response =
+            AppError::ServiceUnavailable("ETL service unavailable".to_string()).into_response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers()[header::RETRY_AFTER],
+            SERVICE_UNAVAILABLE_RETRY_AFTER_SECS.to_string()
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "SERVICE_UNAVAILABLE");
+        assert_eq!(body["error"]["message"], "ETL service unavailable");
+    }
+
+    #[test]
+    fn upstream_retry_after_accepts_seconds() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(upstream_retry_after_secs(&headers), 7);
+        assert_eq!(
+            upstream_retry_after_secs(&HeaderMap::new()),
+            UPSTREAM_RATE_LIMIT_RETRY_AFTER_SECS
+        );
     }
 }
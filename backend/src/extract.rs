@@ -0,0 +1,191 @@
+use axum::{
+    extract::{rejection::JsonRejection, FromRequest, OptionalFromRequest, Request},
+    http::{header, StatusCode},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use std::error::Error;
+
+use crate::error::AppError;
+
+/// `Json<T>` whose rejections are reported as `AppError::Validation`.
+///
+/// axum's own `Json` rejections are plain-text responses; this keeps
+/// malformed or incomplete bodies, missing or non-JSON content types and
+/// oversized bodies in the usual error envelope and names the offending
+/// field where serde reports one.
+///
+/// As `Option<ValidatedJson<T>>` the body is optional: a request without a
+/// `Content-Type` yields `None`, while a present but invalid body is rejected.
+pub struct ValidatedJson<T>(pub T);
+
+impl<S, T> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let content_type = content_type(&req);
+        match <Json<T> as FromRequest<S>>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(Self(value)),
+            Err(rejection) => Err(AppError::Validation(rejection_message(
+                &rejection,
+                content_type.as_deref(),
+            ))),
+        }
+    }
+}
+
+impl<S, T> OptionalFromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        let content_type = content_type(&req);
+        match <Json<T> as OptionalFromRequest<S>>::from_request(req, state).await {
+            Ok(value) => Ok(value.map(|Json(value)| Self(value))),
+            Err(rejection) => Err(AppError::Validation(rejection_message(
+                &rejection,
+                content_type.as_deref(),
+            ))),
+        }
+    }
+}
+
+fn content_type(req: &Request) -> Option<String> {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+fn rejection_message(rejection: &JsonRejection, content_type: Option<&str>) -> String {
+    match rejection {
+        // The source carries serde's message, prefixed with the field path
+        // when there is one, e.g. "username: invalid type: integer `1`, ..."
+        JsonRejection::JsonDataError(e) => match e.source() {
+            Some(source) => format!("Invalid request body: {}", source),
+            None => e.body_text(),
+        },
+        JsonRejection::JsonSyntaxError(_) => "Request body is not valid JSON".to_string(),
+        JsonRejection::MissingJsonContentType(_) => match content_type {
+            Some(content_type) => format!(
+                "Expected a request with `Content-Type: application/json`, got `{}`",
+                content_type
+            ),
+            None => {
+                "Expected a request with `Content-Type: application/json`, got none".to_string()
+            }
+        },
+        JsonRejection::BytesRejection(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            "Request body is too large".to_string()
+        }
+        other => other.body_text(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use serde_json::{json, Value};
+
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn login_without_password_is_a_validation_error() {
+        let app = test_support::spawn(test_support::config()).await;
+
+        let response = app
+            .client
+            .post(app.url("/api/v1/auth/login"))
+            .json(&json!({ "username": "alice" }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"]["code"], "VALIDATION_ERROR");
+        let message = body["error"]["message"].as_str().unwrap();
+        assert!(message.contains("password"), "{}", message);
+    }
+
+    /// Status and error of a `path` request with `body` and an optional content type
+    async fn post(
+        app: &test_support::TestApp,
+        path: &str,
+        content_type: Option<&str>,
+        body: String,
+    ) -> (StatusCode, Value) {
+        let mut request = app.client.post(app.url(path)).body(body);
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+        let response = request.send().await.unwrap();
+        let status = response.status();
+        let body: Value = response.json().await.unwrap();
+        (status, body["error"].clone())
+    }
+
+    #[tokio::test]
+    async fn json_routes_require_a_json_content_type() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (_, token) = app.signed_in("user").await;
+        let login = json!({ "username": "alice", "password": "secret" }).to_string();
+
+        let (status, error) = post(
+            &app,
+            "/api/v1/auth/login",
+            Some("text/plain"),
+            login.clone(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "VALIDATION_ERROR");
+        assert_eq!(
+            error["message"],
+            "Expected a request with `Content-Type: application/json`, got `text/plain`"
+        );
+
+        let (status, error) = post(&app, "/api/v1/auth/login", None, login).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            error["message"],
+            "Expected a request with `Content-Type: application/json`, got none"
+        );
+
+        let chat = app
+            .client
+            .post(app.url("/api/v1/chat/stream"))
+            .bearer_auth(&token)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("query=hello")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(chat.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn oversized_json_body_is_a_validation_error() {
+        let app = test_support::spawn(test_support::config()).await;
+        let body = json!({ "username": "a".repeat(3 * 1024 * 1024), "password": "secret" });
+
+        let (status, error) = post(
+            &app,
+            "/api/v1/auth/login",
+            Some("application/json"),
+            body.to_string(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "VALIDATION_ERROR");
+        assert_eq!(error["message"], "Request body is too large");
+    }
+}
@@ -0,0 +1,86 @@
+use axum::http::HeaderName;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Client-chosen key that makes retried requests safe
+pub static IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+
+const KEY_PREFIX: &str = "idempotency:";
+
+/// How long a key stays claimed by a request that never completes, e.g. because
+/// the gateway restarted mid-upload
+const PENDING_TTL_SECS: u64 = 600;
+
+/// Outcome of claiming an idempotency key
+pub enum Claim {
+    /// First use of the key; the caller should process the request
+    Started,
+    /// An earlier request with this key is still being processed
+    InProgress,
+    /// An earlier request with this key succeeded with this response body
+    Completed(Value),
+}
+
+/// Redis key for a client-supplied idempotency key, kept apart per user and operation.
+pub fn key(operation: &str, user_id: Uuid, idempotency_key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(idempotency_key);
+    format!(
+        "{}{}:{}:{:x}",
+        KEY_PREFIX,
+        operation,
+        user_id,
+        hasher.finalize()
+    )
+}
+
+/// Claim `key` for a new request, or report what happened to the earlier one.
+pub async fn claim(redis: &ConnectionManager, key: &str) -> Result<Claim, redis::RedisError> {
+    let mut conn = redis.clone();
+    let pending = json!({ "status": "pending" }).to_string();
+
+    let claimed: bool = redis::cmd("SET")
+        .arg(key)
+        .arg(pending)
+        .arg("NX")
+        .arg("EX")
+        .arg(PENDING_TTL_SECS)
+        .query_async::<Option<String>>(&mut conn)
+        .await?
+        .is_some();
+    if claimed {
+        return Ok(Claim::Started);
+    }
+
+    let stored: Option<String> = conn.get(key).await?;
+    let stored: Option<Value> = stored.and_then(|raw| serde_json::from_str(&raw).ok());
+    match stored {
+        Some(mut entry) if entry["status"] == "completed" => {
+            Ok(Claim::Completed(entry["response"].take()))
+        }
+        // Expired or unreadable between SET and GET; treat like a request in flight
+        _ => Ok(Claim::InProgress),
+    }
+}
+
+/// Store the response of a successful request, replayed for `ttl_secs`.
+pub async fn complete(
+    redis: &ConnectionManager,
+    key: &str,
+    response: &Value,
+    ttl_secs: u64,
+) -> Result<(), redis::RedisError> {
+    let mut conn = redis.clone();
+    let entry = json!({ "status": "completed", "response": response });
+    conn.set_ex::<_, _, ()>(key, entry.to_string(), ttl_secs)
+        .await
+}
+
+/// Release the claim of a failed request so the client can retry with the same key.
+pub async fn release(redis: &ConnectionManager, key: &str) -> Result<(), redis::RedisError> {
+    let mut conn = redis.clone();
+    conn.del::<_, ()>(key).await
+}
@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::error::AppError;
+use crate::AppState;
+
+/// Probes and metrics stay available while the gateway is shedding load,
+/// so an overloaded instance is not mistaken for a dead one
+const EXEMPT_PATHS: &[&str] = &[
+    "/health",
+    "/api/v1/health/live",
+    "/api/v1/health/ready",
+    "/api/v1/metrics",
+];
+
+/// Reject requests with 503 once `max_inflight_requests` are being handled.
+///
+/// The permit is held until the handler returns its response, so an SSE
+/// stream only counts while it is being set up, not while its body streams.
+pub async fn load_shed(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if state.config.max_inflight_requests == 0 || EXEMPT_PATHS.contains(&req.uri().path()) {
+        return Ok(next.run(req).await);
+    }
+
+    let Ok(_permit) = state.inflight.try_acquire() else {
+        tracing::warn!(
+            max_inflight_requests = state.config.max_inflight_requests,
+            "Too many in-flight requests, shedding load"
+        );
+        return Err(AppError::ServiceUnavailable(
+            "Server is busy, please retry shortly".to_string(),
+        ));
+    };
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Json, Router};
+    use reqwest::StatusCode;
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::Notify;
+
+    use super::*;
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn request_over_the_inflight_limit_is_shed() {
+        let arrived = Arc::new(AtomicUsize::new(0));
+        let release = Arc::new(Notify::new());
+        let etl = Router::new().route(
+            "/api/v1/documents",
+            get({
+                let (arrived, release) = (arrived.clone(), release.clone());
+                move || async move {
+                    arrived.fetch_add(1, Ordering::SeqCst);
+                    release.notified().await;
+                    Json(json!({ "success": true, "data": [] }))
+                }
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        config.max_inflight_requests = 2;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+        let list = || {
+            app.client
+                .get(app.url("/api/v1/documents"))
+                .bearer_auth(&token)
+                .send()
+        };
+
+        let held: Vec<_> = (0..2).map(|_| tokio::spawn(list())).collect();
+        while arrived.load(Ordering::SeqCst) < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let shed = list().await.unwrap();
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = shed.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "SERVICE_UNAVAILABLE");
+        let probe = app
+            .client
+            .get(app.url("/api/v1/health/live"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(probe.status(), StatusCode::OK);
+
+        release.notify_waiters();
+        for request in held {
+            assert_eq!(request.await.unwrap().unwrap().status(), StatusCode::OK);
+        }
+    }
+}
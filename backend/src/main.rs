@@ -1,73 +1,337 @@
 use axum::{
+    http::{header, HeaderValue, Method},
+    middleware,
     response::Json,
     routing::get,
     Router,
 };
 use serde_json::{json, Value};
 use sqlx::postgres::PgPoolOptions;
+use opentelemetry::trace::TracerProvider as _;
+use std::future::IntoFuture;
 use std::sync::Arc;
-use tower_http::cors::{Any, CorsLayer};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, Semaphore};
+use tower_http::compression::predicate::{NotForContentType, Predicate};
+use tower_http::compression::{CompressionLayer, DefaultPredicate};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
+mod audit;
 mod auth;
+mod batch;
+mod circuit_breaker;
+mod client_ip;
 mod config;
+mod db;
 mod error;
+mod extract;
+mod idempotency;
+mod load_shed;
+mod metrics;
+mod migrate;
 mod models;
+mod moderation;
+mod rate_limit;
+mod redact;
+mod request_id;
 mod routes;
+mod search_cache;
+mod shutdown;
+mod slow_request;
+mod telemetry;
+#[cfg(test)]
+mod test_support;
+mod timeout;
+mod upstream_body;
 
 pub struct AppState {
     pub db: sqlx::PgPool,
+    pub redis: redis::aio::ConnectionManager,
+    /// Shared HTTP client for upstream services (ETL / LLM)
+    pub http: reqwest::Client,
+    pub readiness: routes::health::ReadinessCache,
+    pub chat_streams: routes::chat::ActiveStreams,
+    /// Guards chat requests to the LLM service
+    pub llm_breaker: circuit_breaker::CircuitBreaker,
+    pub query_blocklist: moderation::QueryBlocklist,
+    /// Permits for `max_inflight_requests`
+    pub inflight: Semaphore,
+    pub jwt_keys: auth::jwt::JwtKeys,
     pub config: config::Config,
+    /// When the gateway started, for the uptime in `/admin/stats`
+    pub started_at: Instant,
+}
+
+impl AppState {
+    pub fn new(
+        config: config::Config,
+        jwt_keys: auth::jwt::JwtKeys,
+        db: sqlx::PgPool,
+        redis: redis::aio::ConnectionManager,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            db,
+            redis,
+            http: build_http_client(&config)?,
+            readiness: Default::default(),
+            chat_streams: Default::default(),
+            llm_breaker: circuit_breaker::CircuitBreaker::new(
+                "llm",
+                config.llm_breaker_failure_threshold,
+                Duration::from_secs(config.llm_breaker_window_secs),
+                Duration::from_secs(config.llm_breaker_cooldown_secs),
+            ),
+            query_blocklist: moderation::QueryBlocklist::load(config.query_blocklist_path.clone())?,
+            inflight: Semaphore::new(config.max_inflight_requests),
+            jwt_keys,
+            config,
+            started_at: Instant::now(),
+        })
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing
+    // Loaded first so .env can also configure logging and trace export
+    dotenvy::dotenv().ok();
+
+    // Initialize tracing; spans are additionally exported over OTLP when configured.
+    // RUST_LOG takes precedence over LOG_LEVEL, which sets the level of our own targets.
+    let log_level = std::env::var("LOG_LEVEL").unwrap_or_else(|_| "debug".to_string());
+    let fmt_layer = match std::env::var("LOG_FORMAT").as_deref().unwrap_or("json") {
+        "json" => tracing_subscriber::fmt::layer().json().boxed(),
+        "pretty" => tracing_subscriber::fmt::layer().pretty().boxed(),
+        other => return Err(format!("LOG_FORMAT must be json or pretty: {}", other).into()),
+    };
+    let tracer_provider = telemetry::init_tracer()?;
     tracing_subscriber::registry()
         .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "api_gateway=debug,tower_http=debug".into()),
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+                format!("api_gateway={0},tower_http={0}", log_level).into()
+            }),
+        )
+        .with(fmt_layer)
+        .with(
+            tracer_provider
+                .as_ref()
+                .map(|p| tracing_opentelemetry::layer().with_tracer(p.tracer("api-gateway"))),
         )
-        .with(tracing_subscriber::fmt::layer().json())
         .init();
+    if tracer_provider.is_some() {
+        tracing::info!("Exporting traces over OTLP");
+    }
+
+    metrics::init();
 
     // Load config
-    dotenvy::dotenv().ok();
-    let config = config::Config::from_env()?;
+    let config = config::Config::load()?;
     let listen_addr = format!("0.0.0.0:{}", config.port);
 
+    let jwt_keys = auth::jwt::JwtKeys::from_config(&config)?;
+    tracing::info!("Signing JWTs with {:?}", jwt_keys.algorithm());
+
     // Database pool
-    let db = PgPoolOptions::new()
-        .max_connections(10)
+    let db = db_pool_options(&config)
         .connect(&config.database_url)
         .await?;
 
     tracing::info!("Connected to PostgreSQL");
 
-    let state = Arc::new(AppState { db, config });
+    if config.run_migrations {
+        migrate::run(&db).await?;
+    }
+
+    // Redis (token denylist)
+    let redis = redis::Client::open(config.redis_url.as_str())?
+        .get_connection_manager()
+        .await?;
+
+    tracing::info!("Connected to Redis");
+
+    let state = Arc::new(AppState::new(config, jwt_keys, db, redis)?);
+
+    let db = state.db.clone();
+    let drain_timeout = Duration::from_secs(state.config.shutdown_drain_secs);
+
+    let app = app(state)?;
 
+    tracing::info!("Starting API Gateway on {}", listen_addr);
+    let listener = shutdown::TrackedListener::new(tokio::net::TcpListener::bind(&listen_addr).await?);
+    let open_connections = listener.open_connections();
+
+    // On SIGTERM/SIGINT stop accepting connections and let in-flight requests
+    // (including SSE streams) finish, for at most `drain_timeout`
+    let draining = Arc::new(Notify::new());
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<client_ip::PeerAddr>(),
+    )
+        .with_graceful_shutdown({
+            let draining = draining.clone();
+            async move {
+                shutdown::signal().await;
+                draining.notify_one();
+            }
+        })
+        .into_future();
+    tokio::pin!(server);
+
+    tokio::select! {
+        result = &mut server => result?,
+        _ = draining.notified() => {
+            tracing::info!(
+                open_connections = open_connections.get(),
+                drain_timeout_secs = drain_timeout.as_secs(),
+                "Shutdown signal received, draining connections"
+            );
+            match tokio::time::timeout(drain_timeout, &mut server).await {
+                Ok(result) => result?,
+                Err(_) => tracing::warn!(
+                    open_connections = open_connections.get(),
+                    "Drain timeout elapsed, closing remaining connections"
+                ),
+            }
+        }
+    }
+
+    db.close().await;
+    tracing::info!("API Gateway stopped");
+
+    // Flush spans still buffered in the batch exporter
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            eprintln!("Failed to flush traces: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// The gateway's routes with every middleware layer applied.
+fn app(state: Arc<AppState>) -> Result<Router, Box<dyn std::error::Error>> {
     // CORS
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    let cors = build_cors_layer(&state.config)?;
 
     // Router
     let app = Router::new()
         .route("/health", get(health_check))
         .nest("/api/v1", routes::api_routes(state.clone()))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            timeout::request_timeout,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            load_shed::load_shed,
+        ))
+        .layer(middleware::from_fn(metrics::track_metrics))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            slow_request::log_slow_requests,
+        ))
         .layer(cors)
-        .layer(TraceLayer::new_for_http())
+        .layer(compression_layer())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_id::make_span)
+                .on_request(request_id::on_request),
+        )
+        .layer(middleware::from_fn(request_id::request_id))
         .with_state(state);
+    Ok(app)
+}
 
-    tracing::info!("Starting API Gateway on {}", listen_addr);
-    let listener = tokio::net::TcpListener::bind(&listen_addr).await?;
-    axum::serve(listener, app).await?;
+/// gzip / brotli per `Accept-Encoding`.
+///
+/// Chat SSE streams are never compressed: the encoder would hold back events
+/// until it has buffered enough to emit a block.
+fn compression_layer() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new()
+        .gzip(true)
+        .br(true)
+        .compress_when(DefaultPredicate::new().and(NotForContentType::SSE))
+}
 
-    Ok(())
+/// Postgres pool sizing and timeouts from the `DB_*` settings.
+///
+/// Connections are pinged before use so a DB restart doesn't surface as 500s.
+fn db_pool_options(config: &config::Config) -> PgPoolOptions {
+    PgPoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .min_connections(config.db_min_connections)
+        .acquire_timeout(Duration::from_secs(config.db_acquire_timeout_secs))
+        .idle_timeout(Duration::from_secs(config.db_idle_timeout_secs))
+        .test_before_acquire(true)
+}
+
+/// Upstream HTTP client shared by all handlers; per-request timeouts are set by each caller.
+///
+/// Plain-HTTP upstreams are spoken to over HTTP/1.1 unless
+/// `upstream_http2_prior_knowledge` is set; then every connection starts
+/// straight away with HTTP/2, which only works against upstreams that accept
+/// cleartext HTTP/2 (h2c).
+fn build_http_client(config: &config::Config) -> reqwest::Result<reqwest::Client> {
+    let builder = reqwest::Client::builder()
+        .user_agent(concat!("api-gateway/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_millis(config.upstream_connect_timeout_ms))
+        .pool_max_idle_per_host(config.http_pool_max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(config.http_pool_idle_timeout_secs));
+
+    let builder = if config.upstream_http2_prior_knowledge {
+        builder.http2_prior_knowledge()
+    } else {
+        builder
+    };
+    builder.build()
+}
+
+/// Build the CORS layer from `CORS_ALLOWED_ORIGIN` and `CORS_MAX_AGE_SECS`.
+///
+/// When no origin is configured, debug builds fall back to a permissive
+/// policy for local development; release builds allow no cross-origin requests.
+fn build_cors_layer(config: &config::Config) -> Result<CorsLayer, Box<dyn std::error::Error>> {
+    let mut cors = CorsLayer::new()
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .expose_headers([request_id::REQUEST_ID_HEADER.clone()]);
+    if config.cors_max_age_secs > 0 {
+        // Lets browsers skip the preflight before each chat or upload request
+        cors = cors.max_age(Duration::from_secs(config.cors_max_age_secs));
+    }
+
+    if config.cors_allowed_origins.is_empty() {
+        if cfg!(debug_assertions) {
+            tracing::warn!("CORS_ALLOWED_ORIGIN is not set, allowing any origin (debug build)");
+            return Ok(cors.allow_origin(Any).allow_headers(Any));
+        }
+        tracing::warn!("CORS_ALLOWED_ORIGIN is not set, cross-origin requests will be rejected");
+    }
+
+    let origins = config
+        .cors_allowed_origins
+        .iter()
+        .map(|o| o.parse::<HeaderValue>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(cors
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            request_id::REQUEST_ID_HEADER.clone(),
+            idempotency::IDEMPOTENCY_KEY_HEADER.clone(),
+        ])
+        .allow_credentials(true))
 }
 
+/// GET /health - Simple liveness alias kept for existing probes
 async fn health_check() -> Json<Value> {
     Json(json!({
         "status": "healthy",
@@ -75,3 +339,220 @@ async fn health_check() -> Json<Value> {
         "version": env!("CARGO_PKG_VERSION")
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use axum::http::{HeaderMap, Request, StatusCode, Version};
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn preflight(origin: &str) -> axum::response::Response {
+        preflight_with(test_support::config(), origin).await
+    }
+
+    /// A CORS preflight from `origin` to a route behind the layer built from
+    /// `config`, with `https://app.example.com` allowed
+    async fn preflight_with(mut config: config::Config, origin: &str) -> axum::response::Response {
+        config.cors_allowed_origins = vec!["https://app.example.com".to_string()];
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(build_cors_layer(&config).unwrap());
+
+        let request = Request::options("/")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn listed_origin_is_echoed() {
+        let response = preflight("https://app.example.com").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+            "true"
+        );
+    }
+
+    #[tokio::test]
+    async fn unlisted_origin_is_not_allowed() {
+        let response = preflight("https://evil.example.com").await;
+        assert!(response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn preflight_can_be_cached_for_the_configured_time() {
+        let mut config = test_support::config();
+        config.cors_max_age_secs = 600;
+        let response = preflight_with(config, "https://app.example.com").await;
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_MAX_AGE], "600");
+
+        let mut config = test_support::config();
+        config.cors_max_age_secs = 0;
+        let response = preflight_with(config, "https://app.example.com").await;
+        assert!(response
+            .headers()
+            .get(header::ACCESS_CONTROL_MAX_AGE)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn handlers_call_upstreams_with_the_shared_client() {
+        let user_agents = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let etl = Router::new().route(
+            "/api/v1/documents",
+            get({
+                let user_agents = user_agents.clone();
+                move |headers: HeaderMap| async move {
+                    user_agents
+                        .lock()
+                        .unwrap()
+                        .push(headers[header::USER_AGENT].to_str().unwrap().to_string());
+                    axum::Json(serde_json::json!({ "success": true, "data": [] }))
+                }
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+
+        for _ in 0..2 {
+            let response = app
+                .client
+                .get(app.url("/api/v1/documents"))
+                .bearer_auth(&token)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+        }
+
+        // Only the client built by `build_http_client` sends this user agent
+        let expected = concat!("api-gateway/", env!("CARGO_PKG_VERSION"));
+        assert_eq!(*user_agents.lock().unwrap(), vec![expected, expected]);
+    }
+
+    #[tokio::test]
+    async fn json_responses_are_compressed_for_clients_that_accept_it() {
+        let documents: Vec<_> = (0..20)
+            .map(|i| serde_json::json!({ "id": i, "file_name": format!("manual-{}.pdf", i) }))
+            .collect();
+        let etl = Router::new().route(
+            "/api/v1/documents",
+            get(move || async move {
+                axum::Json(serde_json::json!({ "success": true, "data": documents }))
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+        let list = |accept_encoding: &'static str| {
+            app.client
+                .get(app.url("/api/v1/documents"))
+                .bearer_auth(&token)
+                .header(header::ACCEPT_ENCODING, accept_encoding)
+                .send()
+        };
+
+        let gzipped = list("gzip").await.unwrap();
+        assert_eq!(gzipped.status(), reqwest::StatusCode::OK);
+        assert_eq!(gzipped.headers()[header::CONTENT_ENCODING], "gzip");
+
+        let plain = list("identity").await.unwrap();
+        assert!(plain.headers().get(header::CONTENT_ENCODING).is_none());
+        let body: Value = plain.json().await.unwrap();
+        assert_eq!(body["data"].as_array().unwrap().len(), 20);
+    }
+
+    /// HTTP version and client address of two successive requests made by the
+    /// client built from `config`
+    async fn upstream_connections(config: &config::Config) -> Vec<(Version, SocketAddr)> {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let upstream = Router::new().route(
+            "/",
+            get({
+                let seen = seen.clone();
+                move |ConnectInfo(peer): ConnectInfo<SocketAddr>, request: Request<Body>| async move {
+                    seen.lock().unwrap().push((request.version(), peer));
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            axum::serve(
+                listener,
+                upstream.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .into_future(),
+        );
+
+        let client = build_http_client(config).unwrap();
+        for _ in 0..2 {
+            let response = client
+                .get(format!("http://{}/", addr))
+                .send()
+                .await
+                .unwrap();
+            assert!(response.status().is_success());
+        }
+        let seen = seen.lock().unwrap().clone();
+        seen
+    }
+
+    #[tokio::test]
+    async fn http_client_applies_the_pool_and_http2_settings() {
+        let pooled = upstream_connections(&test_support::config()).await;
+        assert_eq!(pooled[0].0, Version::HTTP_11);
+        // The idle connection is reused
+        assert_eq!(pooled[0].1, pooled[1].1);
+
+        let mut config = test_support::config();
+        config.http_pool_max_idle_per_host = 0;
+        let unpooled = upstream_connections(&config).await;
+        assert_ne!(unpooled[0].1, unpooled[1].1);
+
+        let mut config = test_support::config();
+        config.upstream_http2_prior_knowledge = true;
+        let http2 = upstream_connections(&config).await;
+        assert_eq!(http2[0].0, Version::HTTP_2);
+        assert_eq!(http2[0].1, http2[1].1);
+    }
+
+    #[test]
+    fn pool_options_come_from_the_db_settings() {
+        let config = config::Config::load_from(|name| {
+            let value = match name {
+                "DB_MAX_CONNECTIONS" => "25",
+                "DB_MIN_CONNECTIONS" => "3",
+                "DB_ACQUIRE_TIMEOUT_SECS" => "7",
+                "DB_IDLE_TIMEOUT_SECS" => "90",
+                _ => return None,
+            };
+            Some(value.to_string())
+        })
+        .unwrap();
+
+        let options = db_pool_options(&config);
+        assert_eq!(options.get_max_connections(), 25);
+        assert_eq!(options.get_min_connections(), 3);
+        assert_eq!(options.get_acquire_timeout(), Duration::from_secs(7));
+        assert_eq!(options.get_idle_timeout(), Some(Duration::from_secs(90)));
+        assert!(options.get_test_before_acquire());
+    }
+}
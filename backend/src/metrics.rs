@@ -0,0 +1,139 @@
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+static HTTP_REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        Opts::new("http_requests_total", "Total HTTP requests by route and status"),
+        &["method", "route", "status"],
+    ))
+});
+
+static HTTP_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "http_request_duration_seconds",
+            "HTTP request latency in seconds",
+        ),
+        &["method", "route"],
+    ))
+});
+
+static SSE_ACTIVE_STREAMS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register(IntGauge::new("sse_active_streams", "Currently open SSE chat streams"))
+});
+
+static UPSTREAM_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "upstream_request_duration_seconds",
+            "Latency of calls to upstream services in seconds",
+        ),
+        &["service", "operation"],
+    ))
+});
+
+static LLM_STREAM_PROTOCOL_ERRORS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(IntCounter::new(
+        "llm_stream_protocol_errors_total",
+        "LLM stream payloads that matched no known shape",
+    ))
+});
+
+fn register<M>(metric: Result<M, prometheus::Error>) -> M
+where
+    M: prometheus::core::Collector + Clone + 'static,
+{
+    let metric = metric.expect("metric definition is valid");
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .expect("metric is registered once");
+    metric
+}
+
+/// Register all metrics up front so they are exported before first use.
+pub fn init() {
+    LazyLock::force(&HTTP_REQUESTS_TOTAL);
+    LazyLock::force(&HTTP_REQUEST_DURATION);
+    LazyLock::force(&SSE_ACTIVE_STREAMS);
+    LazyLock::force(&UPSTREAM_REQUEST_DURATION);
+    LazyLock::force(&LLM_STREAM_PROTOCOL_ERRORS);
+}
+
+/// Middleware recording the request count and latency of every handler.
+pub async fn track_metrics(req: Request, next: Next) -> Response {
+    // Label by route template rather than raw path to keep cardinality bounded
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = req.method().to_string();
+
+    let start = Instant::now();
+    let response = next.run(req).await;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let status = response.status().as_u16().to_string();
+    HTTP_REQUESTS_TOTAL
+        .with_label_values(&[&method, &route, &status])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[&method, &route])
+        .observe(elapsed);
+
+    response
+}
+
+/// Record how long a call to an upstream service took.
+pub fn observe_upstream(service: &str, operation: &str, elapsed: Duration) {
+    UPSTREAM_REQUEST_DURATION
+        .with_label_values(&[service, operation])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Count an LLM stream payload the gateway could not interpret.
+pub fn record_llm_protocol_error() {
+    LLM_STREAM_PROTOCOL_ERRORS.inc();
+}
+
+/// Keeps `sse_active_streams` incremented for as long as it is alive.
+///
+/// Move it into the stream so the gauge is decremented when the stream is
+/// dropped, including when the client disconnects mid-stream.
+pub struct ActiveStreamGuard;
+
+impl ActiveStreamGuard {
+    pub fn acquire() -> Self {
+        SSE_ACTIVE_STREAMS.inc();
+        Self
+    }
+}
+
+impl Drop for ActiveStreamGuard {
+    fn drop(&mut self) {
+        SSE_ACTIVE_STREAMS.dec();
+    }
+}
+
+/// GET /metrics - Prometheus text exposition format
+pub async fn metrics_handler() -> Response {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer) {
+        tracing::error!("Failed to encode metrics: {}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], buffer).into_response()
+}
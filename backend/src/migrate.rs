@@ -0,0 +1,131 @@
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+/// The schema in `migrations/`, embedded at build time
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Apply pending migrations and log each one applied.
+///
+/// `Migrator::run` takes an advisory lock, so replicas starting together
+/// apply each migration once. A migration that was edited after being
+/// applied fails the checksum check and aborts startup.
+pub async fn run(db: &PgPool) -> Result<(), MigrateError> {
+    let before = applied_versions(db).await?;
+    MIGRATOR.run(db).await?;
+
+    let mut applied = 0;
+    for migration in MIGRATOR.iter() {
+        if !before.contains(&migration.version) {
+            tracing::info!(
+                version = migration.version,
+                description = %migration.description,
+                "Applied database migration"
+            );
+            applied += 1;
+        }
+    }
+    if applied == 0 {
+        tracing::info!("Database schema is up to date");
+    }
+    Ok(())
+}
+
+/// Versions recorded in `_sqlx_migrations`; empty before the first run
+async fn applied_versions(db: &PgPool) -> Result<HashSet<i64>, MigrateError> {
+    let table_exists: bool =
+        sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(db)
+            .await?;
+    if !table_exists {
+        return Ok(HashSet::new());
+    }
+
+    let versions: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+            .fetch_all(db)
+            .await?;
+    Ok(versions.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::migrate::MigrateDatabase;
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+    use crate::test_support;
+
+    /// A new, empty database next to the test database, and its URL
+    async fn fresh_database() -> (PgPool, String) {
+        let test_url = test_support::config().database_url;
+        let (server, _) = test_url.rsplit_once('/').unwrap();
+        let url = format!("{}/migrate_test_{}", server, uuid::Uuid::new_v4().simple());
+        sqlx::Postgres::create_database(&url).await.unwrap();
+
+        let db = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&url)
+            .await
+            .unwrap();
+        (db, url)
+    }
+
+    #[tokio::test]
+    async fn migrations_apply_to_a_fresh_database_once() {
+        let (db, url) = fresh_database().await;
+        run(&db).await.unwrap();
+        let applied = applied_versions(&db).await.unwrap();
+        assert_eq!(applied.len(), MIGRATOR.iter().count());
+        for table in [
+            "users",
+            "refresh_tokens",
+            "chat_sessions",
+            "audit_log",
+            "api_keys",
+        ] {
+            let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+                .bind(table)
+                .fetch_one(&db)
+                .await
+                .unwrap();
+            assert!(exists, "{} was not created", table);
+        }
+
+        // Nothing left to apply the second time
+        run(&db).await.unwrap();
+        assert_eq!(applied_versions(&db).await.unwrap(), applied);
+
+        db.close().await;
+        sqlx::Postgres::drop_database(&url).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn migrations_apply_over_a_schema_created_without_them() {
+        // Volumes created by the old docker/postgres/init scripts have the
+        // initial schema but no `_sqlx_migrations` table
+        let (db, url) = fresh_database().await;
+        let init = MIGRATOR.iter().next().unwrap();
+        sqlx::raw_sql(&init.sql).execute(&db).await.unwrap();
+
+        run(&db).await.unwrap();
+        assert_eq!(
+            applied_versions(&db).await.unwrap().len(),
+            MIGRATOR.iter().count()
+        );
+        let admins: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE username = 'admin'")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(admins, 1);
+        let api_keys_exists: bool =
+            sqlx::query_scalar("SELECT to_regclass('api_keys') IS NOT NULL")
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert!(api_keys_exists);
+
+        db.close().await;
+        sqlx::Postgres::drop_database(&url).await.unwrap();
+    }
+}
@@ -0,0 +1,62 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// A row of the conversation history list (`chat_sessions` with message count)
+#[derive(Debug, Serialize, FromRow)]
+pub struct ConversationSummary {
+    pub id: Uuid,
+    pub title: Option<String>,
+    pub message_count: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Position in the conversation list, after the conversation it was taken from.
+///
+/// Sent to clients as an opaque URL-safe string; the list is ordered by
+/// `updated_at` then `id`, both descending.
+#[derive(Debug, Clone, Copy)]
+pub struct ConversationCursor {
+    pub updated_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl ConversationCursor {
+    pub fn after(conversation: &ConversationSummary) -> Self {
+        Self {
+            updated_at: conversation.updated_at,
+            id: conversation.id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        // Microseconds match the precision of Postgres timestamps
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}",
+            self.updated_at.timestamp_micros(),
+            self.id
+        ))
+    }
+
+    /// `None` for anything `encode` didn't produce
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let decoded = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+        let (micros, id) = decoded.split_once(':')?;
+        Some(Self {
+            updated_at: DateTime::from_timestamp_micros(micros.parse().ok()?)?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Message {
+    pub id: Uuid,
+    pub role: String,
+    pub content: String,
+    pub sources: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
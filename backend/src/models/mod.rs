@@ -1 +1,3 @@
+pub mod api_key;
+pub mod conversation;
 pub mod user;
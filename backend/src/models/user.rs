@@ -14,6 +14,8 @@ pub struct User {
     pub role: String,
     pub department: Option<String>,
     pub is_active: bool,
+    /// Set when an admin reset the password; cleared when the user changes it
+    pub must_change_password: bool,
     pub last_login_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -26,6 +28,7 @@ pub struct UserResponse {
     pub display_name: Option<String>,
     pub role: String,
     pub department: Option<String>,
+    pub must_change_password: bool,
 }
 
 impl From<User> for UserResponse {
@@ -36,6 +39,7 @@ impl From<User> for UserResponse {
             display_name: u.display_name,
             role: u.role,
             department: u.department,
+            must_change_password: u.must_change_password,
         }
     }
 }
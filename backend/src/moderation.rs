@@ -0,0 +1,146 @@
+use regex::{RegexSet, RegexSetBuilder};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// Queries refused before retrieval or generation, read from `query_blocklist_path`.
+///
+/// The file holds one pattern per line; blank lines and lines starting with
+/// `#` are skipped. Patterns are regular expressions matched case-insensitively
+/// anywhere in the query, so a plain keyword blocks every query containing it.
+pub struct QueryBlocklist {
+    path: Option<PathBuf>,
+    patterns: RwLock<Arc<Patterns>>,
+}
+
+struct Patterns {
+    lines: Vec<String>,
+    set: RegexSet,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlocklistError {
+    #[error("failed to read {path}: {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("invalid pattern: {0}")]
+    Pattern(#[from] regex::Error),
+}
+
+impl QueryBlocklist {
+    /// Load the blocklist; an unset path blocks nothing.
+    pub fn load(path: Option<PathBuf>) -> Result<Self, BlocklistError> {
+        let patterns = read_patterns(path.as_ref())?;
+        tracing::info!(patterns = patterns.lines.len(), "Loaded query blocklist");
+        Ok(Self {
+            path,
+            patterns: RwLock::new(Arc::new(patterns)),
+        })
+    }
+
+    /// Re-read the file, returning the number of patterns now in effect.
+    ///
+    /// On error the previous patterns stay in effect.
+    pub fn reload(&self) -> Result<usize, BlocklistError> {
+        let patterns = read_patterns(self.path.as_ref())?;
+        let count = patterns.lines.len();
+        *self.patterns.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(patterns);
+        Ok(count)
+    }
+
+    /// The first pattern `query` matches, if any
+    pub fn find_match(&self, query: &str) -> Option<String> {
+        let patterns = self
+            .patterns
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let index = patterns.set.matches(query).into_iter().next()?;
+        Some(patterns.lines[index].clone())
+    }
+}
+
+fn read_patterns(path: Option<&PathBuf>) -> Result<Patterns, BlocklistError> {
+    let contents = match path {
+        Some(path) => std::fs::read_to_string(path).map_err(|source| BlocklistError::Read {
+            path: path.display().to_string(),
+            source,
+        })?,
+        None => String::new(),
+    };
+
+    let lines: Vec<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    let set = RegexSetBuilder::new(&lines)
+        .case_insensitive(true)
+        .build()?;
+
+    Ok(Patterns { lines, set })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A blocklist file with `contents` in the temp directory
+    fn blocklist_file(contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("blocklist-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn patterns_match_case_insensitively_anywhere() {
+        let path = blocklist_file("# weapons\n\nexplosive\n^how to pick .* lock\n");
+        let blocklist = QueryBlocklist::load(Some(path)).unwrap();
+
+        assert_eq!(
+            blocklist.find_match("Making EXPLOSIVES at home"),
+            Some("explosive".to_string())
+        );
+        assert_eq!(
+            blocklist.find_match("How to pick a lock"),
+            Some("^how to pick .* lock".to_string())
+        );
+        assert_eq!(blocklist.find_match("How do I lock the pump?"), None);
+        // Comments are not patterns
+        assert_eq!(blocklist.find_match("weapons"), None);
+    }
+
+    #[test]
+    fn unset_path_blocks_nothing() {
+        let blocklist = QueryBlocklist::load(None).unwrap();
+        assert_eq!(blocklist.find_match("anything"), None);
+        assert_eq!(blocklist.reload().unwrap(), 0);
+    }
+
+    #[test]
+    fn reload_picks_up_changes_and_keeps_the_old_list_on_error() {
+        let path = blocklist_file("explosive\n");
+        let blocklist = QueryBlocklist::load(Some(path.clone())).unwrap();
+
+        std::fs::write(&path, "explosive\nsabotage\n").unwrap();
+        assert_eq!(blocklist.reload().unwrap(), 2);
+        assert!(blocklist.find_match("plan sabotage").is_some());
+
+        std::fs::write(&path, "unclosed (group\n").unwrap();
+        assert!(matches!(
+            blocklist.reload(),
+            Err(BlocklistError::Pattern(_))
+        ));
+        assert!(blocklist.find_match("plan sabotage").is_some());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            blocklist.reload(),
+            Err(BlocklistError::Read { .. })
+        ));
+        assert!(blocklist.find_match("explosive").is_some());
+    }
+}
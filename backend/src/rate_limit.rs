@@ -0,0 +1,156 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use std::sync::Arc;
+
+use crate::auth::middleware::AuthUser;
+use crate::error::AppError;
+use crate::AppState;
+
+const KEY_PREFIX: &str = "ratelimit:";
+const WINDOW_MS: i64 = 60_000;
+
+/// Per-user sliding-window rate limit, layered after `auth_middleware`.
+///
+/// Each request is recorded in a Redis sorted set scored by timestamp;
+/// entries older than the window are trimmed before counting. Redis
+/// failures are logged and the request is allowed through.
+pub async fn rate_limit(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<AuthUser>()
+        .ok_or(AppError::Unauthorized)?;
+    enforce(&state, auth_user).await?;
+
+    Ok(next.run(req).await)
+}
+
+/// Count one request against `auth_user`'s limit, failing with `RateLimited` when over it.
+///
+/// For requests that don't go through the middleware, such as chat queries
+/// sent over a WebSocket.
+pub async fn enforce(state: &AppState, auth_user: &AuthUser) -> Result<(), AppError> {
+    let limit = state.config.rate_limit_for_role(&auth_user.role);
+    if limit == 0 {
+        return Ok(());
+    }
+
+    match check(&state.redis, &auth_user.user_id.to_string(), limit).await {
+        Ok(None) => {}
+        Ok(Some(retry_after_secs)) => {
+            tracing::warn!(
+                user = %auth_user.username,
+                limit,
+                retry_after_secs,
+                "Rate limit exceeded"
+            );
+            return Err(AppError::RateLimited { retry_after_secs });
+        }
+        Err(e) => tracing::warn!("Rate limit check failed, allowing request: {}", e),
+    }
+    Ok(())
+}
+
+/// Record a request and return `Some(retry_after_secs)` when over `limit` per minute.
+async fn check(
+    redis: &redis::aio::ConnectionManager,
+    user_id: &str,
+    limit: u32,
+) -> Result<Option<u64>, redis::RedisError> {
+    let key = format!("{}{}", KEY_PREFIX, user_id);
+    let now = Utc::now().timestamp_millis();
+    let member = format!("{}-{}", now, uuid::Uuid::new_v4());
+    let mut conn = redis.clone();
+
+    let (count,): (u32,) = redis::pipe()
+        .atomic()
+        .zrembyscore(&key, 0, now - WINDOW_MS)
+        .ignore()
+        .zadd(&key, &member, now)
+        .ignore()
+        .zcard(&key)
+        .pexpire(&key, WINDOW_MS)
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+
+    if count <= limit {
+        return Ok(None);
+    }
+
+    // Rejected requests don't consume quota
+    let (oldest,): (Vec<(String, f64)>,) = redis::pipe()
+        .zrem(&key, &member)
+        .ignore()
+        .zrange_withscores(&key, 0, 0)
+        .query_async(&mut conn)
+        .await?;
+
+    let retry_after_ms = oldest
+        .first()
+        .map(|(_, ts)| *ts as i64 + WINDOW_MS - now)
+        .unwrap_or(WINDOW_MS);
+    Ok(Some((retry_after_ms.max(0) as u64).div_ceil(1000).max(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    use crate::test_support::{self, TestApp};
+
+    /// A rate-limited chat request; its empty query fails validation without reaching an upstream
+    async fn chat(app: &TestApp, token: &str) -> reqwest::Response {
+        app.client
+            .post(app.url("/api/v1/chat/stream"))
+            .bearer_auth(token)
+            .json(&json!({ "query": "" }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn request_over_the_limit_is_rejected() {
+        let mut config = test_support::config();
+        config.rate_limit_rpm = 2;
+        config.rate_limit_role_rpm.insert("admin".to_string(), 3);
+        let app = test_support::spawn(config).await;
+        let (_, user_token) = app.signed_in("user").await;
+        let (_, admin_token) = app.signed_in("admin").await;
+
+        for _ in 0..2 {
+            assert_eq!(
+                chat(&app, &user_token).await.status(),
+                StatusCode::BAD_REQUEST
+            );
+        }
+        let rejected = chat(&app, &user_token).await;
+        assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = rejected.headers()["retry-after"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=60).contains(&retry_after));
+
+        for _ in 0..3 {
+            assert_eq!(
+                chat(&app, &admin_token).await.status(),
+                StatusCode::BAD_REQUEST
+            );
+        }
+        assert_eq!(
+            chat(&app, &admin_token).await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+}
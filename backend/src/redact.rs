@@ -0,0 +1,141 @@
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+use regex::Regex;
+use serde_json::Value;
+use std::sync::LazyLock;
+
+use crate::auth::api_keys::API_KEY_HEADER;
+
+/// Replaces every masked value
+const MASK: &str = "[REDACTED]";
+
+/// JSON fields holding credentials, besides any field ending in `password`
+const SENSITIVE_FIELDS: &[&str] = &["refresh_token", "access_token"];
+
+/// Credentials written as `name=value`, `name: value` or `Bearer value` in
+/// plain text, such as an error page or a form body
+static TEXT_CREDENTIAL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r#"(?i)((?:\b\w*password|\brefresh_token|\baccess_token)["']?\s*[=:]\s*["']?"#,
+        r#"|\bbearer\s+)[^\s"'&,;<]+"#,
+    ))
+    .expect("valid credential pattern")
+});
+
+fn is_sensitive_header(name: &HeaderName) -> bool {
+    // Cookies carry the refresh token
+    [
+        &header::AUTHORIZATION,
+        &header::PROXY_AUTHORIZATION,
+        &header::COOKIE,
+        &header::SET_COOKIE,
+        &API_KEY_HEADER,
+    ]
+    .contains(&name)
+}
+
+fn is_sensitive_field(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with("password") || SENSITIVE_FIELDS.contains(&name.as_str())
+}
+
+/// Copy of `headers` with credentials masked, for logging.
+pub fn headers(headers: &HeaderMap) -> HeaderMap {
+    let mut redacted = headers.clone();
+    for (name, value) in redacted.iter_mut() {
+        if is_sensitive_header(name) {
+            *value = HeaderValue::from_static(MASK);
+        }
+    }
+    redacted
+}
+
+/// Copy of a JSON body with credential fields masked at any depth, for logging.
+pub fn json(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| {
+                    let value = if is_sensitive_field(name) {
+                        Value::String(MASK.to_string())
+                    } else {
+                        json(value)
+                    };
+                    (name.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(json).collect()),
+        other => other.clone(),
+    }
+}
+
+/// A body as text for logging: JSON is masked with [`json`], other text where
+/// it looks like `password=...`, `access_token: ...` or `Bearer ...`.
+pub fn body(text: &str) -> String {
+    match serde_json::from_str::<Value>(text) {
+        Ok(value) => json(&value).to_string(),
+        Err(_) => TEXT_CREDENTIAL
+            .replace_all(text, format!("${{1}}{}", MASK))
+            .into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn credential_fields_are_masked_at_any_depth() {
+        let body = json!({
+            "username": "alice",
+            "password": "hunter2",
+            "refresh_token": "rt",
+            "session": { "access_token": "at", "expires_in": 900 },
+            "changes": [{ "current_password": "old", "New_Password": "new" }],
+        });
+        assert_eq!(
+            json(&body),
+            json!({
+                "username": "alice",
+                "password": MASK,
+                "refresh_token": MASK,
+                "session": { "access_token": MASK, "expires_in": 900 },
+                "changes": [{ "current_password": MASK, "New_Password": MASK }],
+            })
+        );
+    }
+
+    #[test]
+    fn credential_headers_are_masked() {
+        let mut original = HeaderMap::new();
+        original.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        original.insert(&API_KEY_HEADER, "grk_secret".parse().unwrap());
+        original.insert(header::COOKIE, "refresh_token=secret".parse().unwrap());
+        original.insert(header::USER_AGENT, "curl/8".parse().unwrap());
+
+        let redacted = headers(&original);
+        assert_eq!(redacted[header::AUTHORIZATION], MASK);
+        assert_eq!(redacted[&API_KEY_HEADER], MASK);
+        assert_eq!(redacted[header::COOKIE], MASK);
+        assert_eq!(redacted[header::USER_AGENT], "curl/8");
+    }
+
+    #[test]
+    fn credentials_in_plain_text_are_masked() {
+        assert_eq!(
+            body("username=alice&password=hunter2&remember=1"),
+            "username=alice&password=[REDACTED]&remember=1"
+        );
+        assert_eq!(
+            body("upstream said: Authorization: Bearer abc.def"),
+            "upstream said: Authorization: Bearer [REDACTED]"
+        );
+        assert_eq!(
+            body(r#"{"refresh_token":"rt"}"#),
+            r#"{"refresh_token":"[REDACTED]"}"#
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Span;
+use uuid::Uuid;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied id that is accepted; longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Id of the current request, stored in request extensions
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: String;
+}
+
+/// Id of the request being handled on this task, if any.
+///
+/// Lets `AppError` include the id in error bodies without access to the request.
+pub fn current() -> Option<String> {
+    CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Middleware assigning every request an id, taken from `X-Request-Id` when
+/// the client sends a usable one and generated otherwise.
+///
+/// The id is echoed back in the `X-Request-Id` response header. Layer this
+/// outside `TraceLayer` so `make_span` can read it.
+pub async fn request_id(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| is_valid(v))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    req.extensions_mut().insert(RequestId(id.clone()));
+    let mut response = CURRENT_REQUEST_ID.scope(id.clone(), next.run(req)).await;
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER.clone(), value);
+    }
+    response
+}
+
+/// Span for `TraceLayer` carrying the request id, so every log line emitted
+/// while handling the request can be correlated with it.
+pub fn make_span(req: &Request) -> Span {
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.as_str())
+        .unwrap_or_default();
+
+    let span = tracing::info_span!(
+        "request",
+        method = %req.method(),
+        uri = %req.uri(),
+        request_id = %request_id,
+    );
+    crate::telemetry::set_parent_from_headers(&span, req.headers());
+    span
+}
+
+/// `TraceLayer` hook logging the request headers at debug level, with
+/// credentials masked.
+pub fn on_request(req: &Request, _span: &Span) {
+    tracing::debug!(
+        headers = ?crate::redact::headers(req.headers()),
+        "started processing request"
+    );
+}
+
+/// Client ids end up in logs and headers, so only short printable ASCII is kept.
+fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use serde_json::Value;
+
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn error_body_carries_the_request_id() {
+        let app = test_support::spawn(test_support::config()).await;
+        let me = || app.client.get(app.url("/api/v1/auth/me"));
+
+        let generated = me().send().await.unwrap();
+        assert_eq!(generated.status(), StatusCode::UNAUTHORIZED);
+        let header = generated.headers()["x-request-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(uuid::Uuid::parse_str(&header).is_ok());
+        let body: Value = generated.json().await.unwrap();
+        assert_eq!(body["error"]["request_id"], header);
+
+        let supplied = me()
+            .header("X-Request-Id", "client-42")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(supplied.headers()["x-request-id"], "client-42");
+        let body: Value = supplied.json().await.unwrap();
+        assert_eq!(body["error"]["request_id"], "client-42");
+    }
+}
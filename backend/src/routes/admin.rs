@@ -0,0 +1,734 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    Extension,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
+use crate::audit::{self, AuditEvent, ClientInfo};
+use crate::auth::middleware::AuthUser;
+use crate::auth::{api_keys, lockout, password, refresh_tokens, revocation, scopes};
+use crate::db;
+use crate::error::AppError;
+use crate::extract::ValidatedJson;
+use crate::models::api_key::ApiKey;
+use crate::models::user::{User, UserResponse};
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ListUsersQuery {
+    pub role: Option<String>,
+    pub department: Option<String>,
+    pub is_active: Option<bool>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserRequest {
+    pub is_active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    /// Generated and returned in the response when absent
+    pub new_password: Option<String>,
+}
+
+/// Longest API key name, matching the `api_keys.name` column
+const MAX_API_KEY_NAME_CHARS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    /// Owner of the key, whose role it acts with
+    pub user_id: Uuid,
+    pub name: String,
+    /// The owner's role defaults when absent
+    pub scopes: Option<Vec<String>>,
+    /// The key never expires when absent
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListApiKeysQuery {
+    pub user_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// GET /admin/users - List accounts, newest first, with optional filters
+pub async fn list_users(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ListUsersQuery>,
+) -> Result<Json<Value>, AppError> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let users = sqlx::query_as::<_, User>(
+        "SELECT * FROM users \
+         WHERE ($1::text IS NULL OR role = $1) \
+           AND ($2::text IS NULL OR department = $2) \
+           AND ($3::bool IS NULL OR is_active = $3) \
+         ORDER BY created_at DESC, id DESC \
+         LIMIT $4 OFFSET $5",
+    )
+    .bind(&params.role)
+    .bind(&params.department)
+    .bind(params.is_active)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.db)
+    .await?;
+
+    let users: Vec<UserResponse> = users.into_iter().map(UserResponse::from).collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": users,
+        "meta": { "limit": limit, "offset": offset }
+    })))
+}
+
+/// PATCH /admin/users/{id} - Deactivate or reactivate an account
+///
+/// Deactivating also revokes the user's refresh tokens and the access tokens
+/// issued so far, so their sessions end at once.
+pub async fn update_user(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    client: ClientInfo,
+    Path(user_id): Path<Uuid>,
+    ValidatedJson(req): ValidatedJson<UpdateUserRequest>,
+) -> Result<Json<Value>, AppError> {
+    if user_id == auth_user.user_id && !req.is_active {
+        return Err(AppError::Validation(
+            "You cannot deactivate your own account".to_string(),
+        ));
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET is_active = $1, updated_at = NOW() WHERE id = $2 RETURNING *",
+    )
+    .bind(req.is_active)
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let event = if !user.is_active {
+        let revoked = refresh_tokens::revoke_all_for_user(&state.db, user.id).await?;
+        revocation::revoke_all_before_now(
+            &state.redis,
+            user.id,
+            state.config.max_token_lifetime_secs(),
+        )
+        .await?;
+        tracing::info!(
+            admin = %auth_user.username,
+            user = %user.username,
+            revoked,
+            "User deactivated"
+        );
+        AuditEvent::UserDeactivated { user_id: user.id }
+    } else {
+        tracing::info!(admin = %auth_user.username, user = %user.username, "User reactivated");
+        AuditEvent::UserReactivated { user_id: user.id }
+    };
+    audit::record(&state.db, &client, event, auth_user.actor()).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": UserResponse::from(user)
+    })))
+}
+
+/// POST /admin/users/{id}/reset-password - Set a new password for another user
+///
+/// Uses `new_password` when given, otherwise generates a temporary password
+/// and returns it once in the response. Either way the admin knows the
+/// password, so the account is flagged `must_change_password` until its owner
+/// changes it. All of the user's sessions are revoked and any login lockout
+/// is lifted.
+pub async fn reset_password(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    client: ClientInfo,
+    Path(user_id): Path<Uuid>,
+    payload: Option<ValidatedJson<ResetPasswordRequest>>,
+) -> Result<Json<Value>, AppError> {
+    let provided = payload.and_then(|ValidatedJson(req)| req.new_password);
+    let (new_password, generated) = match provided {
+        Some(new_password) => {
+            password::check_policy(&new_password)?;
+            (new_password, false)
+        }
+        None => (password::generate_temporary(), true),
+    };
+    let password_hash = password::hash(&new_password, state.config.bcrypt_cost)?;
+
+    // The new password and the revocation of the sessions opened with the old one go together
+    let (user, revoked_sessions) = db::transaction(&state.db, move |conn| {
+        Box::pin(async move {
+            let user = sqlx::query_as::<_, User>(
+                "UPDATE users SET password_hash = $1, must_change_password = true, \
+                 updated_at = NOW() WHERE id = $2 RETURNING *",
+            )
+            .bind(password_hash)
+            .bind(user_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+            let revoked_sessions = refresh_tokens::revoke_all_for_user(&mut *conn, user.id).await?;
+            Ok((user, revoked_sessions))
+        })
+    })
+    .await?;
+    revocation::revoke_all_before_now(
+        &state.redis,
+        user.id,
+        state.config.max_token_lifetime_secs(),
+    )
+    .await?;
+    lockout::reset(&state.redis, &user.username).await?;
+
+    tracing::info!(
+        admin = %auth_user.username,
+        user = %user.username,
+        generated,
+        revoked_sessions,
+        "Password reset by admin"
+    );
+    audit::record(
+        &state.db,
+        &client,
+        AuditEvent::PasswordReset { user_id: user.id },
+        auth_user.actor(),
+    )
+    .await;
+
+    let mut data = json!({ "user": UserResponse::from(user) });
+    if generated {
+        data["temporary_password"] = json!(new_password);
+    }
+
+    Ok(Json(json!({ "success": true, "data": data })))
+}
+
+/// POST /admin/api-keys - Issue an API key for a user
+///
+/// The key is returned once, in `key`; only its hash is stored. Requests
+/// sending it in `X-API-Key` act as the owning user, limited to the key's
+/// `scopes`, until it expires or is revoked.
+pub async fn create_api_key(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<Value>), AppError> {
+    let name = req.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_API_KEY_NAME_CHARS {
+        return Err(AppError::Validation(format!(
+            "name must be 1 to {} characters",
+            MAX_API_KEY_NAME_CHARS
+        )));
+    }
+    if let Some(scopes) = &req.scopes {
+        scopes::check_known(scopes)?;
+    }
+    if req
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(AppError::Validation(
+            "expires_at must be in the future".to_string(),
+        ));
+    }
+
+    let owner_role: String =
+        sqlx::query_scalar("SELECT role FROM users WHERE id = $1 AND is_active = true")
+            .bind(req.user_id)
+            .fetch_optional(&state.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    let scopes = req.scopes.unwrap_or_else(|| scopes::for_role(&owner_role));
+
+    let key = api_keys::generate();
+    let api_key = sqlx::query_as::<_, ApiKey>(
+        "INSERT INTO api_keys (user_id, name, key_prefix, key_hash, scopes, expires_at, created_by) \
+         SELECT id, $2, $3, $4, $5, $6, $7 FROM users WHERE id = $1 AND is_active = true \
+         RETURNING *",
+    )
+    .bind(req.user_id)
+    .bind(name)
+    .bind(&key.prefix)
+    .bind(&key.hash)
+    .bind(&scopes)
+    .bind(req.expires_at)
+    .bind(auth_user.user_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    tracing::info!(
+        admin = %auth_user.username,
+        user_id = %api_key.user_id,
+        api_key_id = %api_key.id,
+        "API key created"
+    );
+    audit::record(
+        &state.db,
+        &client,
+        AuditEvent::ApiKeyCreated {
+            user_id: api_key.user_id,
+            key_id: api_key.id,
+        },
+        auth_user.actor(),
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "success": true,
+            "data": { "api_key": api_key, "key": key.plaintext }
+        })),
+    ))
+}
+
+/// GET /admin/api-keys - List API keys, newest first, optionally of one user
+pub async fn list_api_keys(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ListApiKeysQuery>,
+) -> Result<Json<Value>, AppError> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let api_keys = sqlx::query_as::<_, ApiKey>(
+        "SELECT * FROM api_keys \
+         WHERE ($1::uuid IS NULL OR user_id = $1) \
+         ORDER BY created_at DESC, id DESC \
+         LIMIT $2 OFFSET $3",
+    )
+    .bind(params.user_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": api_keys,
+        "meta": { "limit": limit, "offset": offset }
+    })))
+}
+
+/// DELETE /admin/api-keys/{id} - Revoke an API key
+///
+/// Takes effect on the next request made with the key. Revoking an already
+/// revoked key succeeds without change.
+pub async fn revoke_api_key(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    client: ClientInfo,
+    Path(key_id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+    let api_key =
+        sqlx::query_as::<_, ApiKey>("UPDATE api_keys SET revoked = true WHERE id = $1 RETURNING *")
+            .bind(key_id)
+            .fetch_optional(&state.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("API key not found".to_string()))?;
+
+    tracing::info!(
+        admin = %auth_user.username,
+        user_id = %api_key.user_id,
+        api_key_id = %api_key.id,
+        "API key revoked"
+    );
+    audit::record(
+        &state.db,
+        &client,
+        AuditEvent::ApiKeyRevoked {
+            user_id: api_key.user_id,
+            key_id: api_key.id,
+        },
+        auth_user.actor(),
+    )
+    .await;
+
+    Ok(Json(json!({ "success": true, "data": api_key })))
+}
+
+/// GET /admin/stats - Snapshot of the gateway's runtime internals
+///
+/// Read from in-process state only, so it is cheap and doesn't touch the
+/// database or upstream services. `inflight_requests` is null when load
+/// shedding is disabled.
+pub async fn stats(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let pool_size = state.db.size();
+    let pool_idle = state.db.num_idle();
+    let inflight_requests = (state.config.max_inflight_requests > 0)
+        .then(|| state.config.max_inflight_requests - state.inflight.available_permits());
+
+    Json(json!({
+        "success": true,
+        "data": {
+            "uptime_secs": state.started_at.elapsed().as_secs(),
+            "db_pool": {
+                "size": pool_size,
+                "idle": pool_idle,
+                "in_use": (pool_size as usize).saturating_sub(pool_idle),
+                "max_connections": state.config.db_max_connections,
+            },
+            "chat_streams": state.chat_streams.len(),
+            "inflight_requests": inflight_requests,
+            "max_inflight_requests": state.config.max_inflight_requests,
+        }
+    }))
+}
+
+/// POST /admin/moderation/reload - Re-read the query blocklist file
+///
+/// A file that can't be read or holds an invalid pattern is rejected and the
+/// previous blocklist stays in effect.
+pub async fn reload_blocklist(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Value>, AppError> {
+    let patterns = state
+        .query_blocklist
+        .reload()
+        .map_err(|e| AppError::Validation(format!("Blocklist not reloaded: {}", e)))?;
+
+    tracing::info!(admin = %auth_user.username, patterns, "Query blocklist reloaded");
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "patterns": patterns }
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use crate::test_support::{self, TestApp, TestUser};
+
+    async fn in_department(app: &TestApp, user: &TestUser, department: &str) {
+        sqlx::query("UPDATE users SET department = $1 WHERE id = $2")
+            .bind(department)
+            .bind(user.id)
+            .execute(&app.state.db)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn only_admins_list_users() {
+        let app = test_support::spawn(test_support::config()).await;
+
+        for role in ["user", "editor"] {
+            let (_, token) = app.signed_in(role).await;
+            let response = app
+                .client
+                .get(app.url("/api/v1/admin/users"))
+                .bearer_auth(&token)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", role);
+        }
+
+        let (_, token) = app.signed_in("admin").await;
+        let response = app
+            .client
+            .get(app.url("/api/v1/admin/users"))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn users_are_filtered_by_role() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (_, token) = app.signed_in("admin").await;
+        let department = format!("dept_{}", Uuid::new_v4().simple());
+        let first = app.user("editor").await;
+        let second = app.user("editor").await;
+        let other = app.user("user").await;
+        for user in [&first, &second, &other] {
+            in_department(&app, user, &department).await;
+        }
+
+        let body: Value = app
+            .client
+            .get(app.url("/api/v1/admin/users"))
+            .query(&[("role", "editor"), ("department", department.as_str())])
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        let usernames: Vec<&str> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|user| user["username"].as_str().unwrap())
+            .collect();
+        // Newest first
+        assert_eq!(
+            usernames,
+            [second.username.as_str(), first.username.as_str()]
+        );
+        assert!(body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|user| user["role"] == "editor"));
+    }
+
+    #[tokio::test]
+    async fn deactivated_user_is_signed_out_until_reactivated() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (_, admin_token) = app.signed_in("admin").await;
+        let (user, token) = app.signed_in("user").await;
+        let set_active = |is_active: bool| {
+            app.client
+                .patch(app.url(&format!("/api/v1/admin/users/{}", user.id)))
+                .bearer_auth(&admin_token)
+                .json(&json!({ "is_active": is_active }))
+                .send()
+        };
+        let login = || {
+            app.client
+                .post(app.url("/api/v1/auth/login"))
+                .json(&json!({
+                    "username": user.username,
+                    "password": test_support::PASSWORD,
+                }))
+                .send()
+        };
+        let me = || {
+            app.client
+                .get(app.url("/api/v1/auth/me"))
+                .bearer_auth(&token)
+                .send()
+        };
+
+        assert_eq!(set_active(false).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(login().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(me().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        assert_eq!(set_active(true).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(login().await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn reset_password_signs_the_user_out_and_flags_the_account() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (_, admin_token) = app.signed_in("admin").await;
+        let user = app.user("user").await;
+        let old_session = app.login(&user).await;
+
+        let response = app
+            .client
+            .post(app.url(&format!("/api/v1/admin/users/{}/reset-password", user.id)))
+            .bearer_auth(&admin_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["data"]["user"]["must_change_password"], true);
+        let temporary_password = body["data"]["temporary_password"].as_str().unwrap();
+
+        let refresh = app
+            .client
+            .post(app.url("/api/v1/auth/refresh"))
+            .json(&json!({ "refresh_token": old_session["refresh_token"] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(refresh.status(), StatusCode::UNAUTHORIZED);
+
+        let login = |password: &str| {
+            app.client
+                .post(app.url("/api/v1/auth/login"))
+                .json(&json!({ "username": user.username, "password": password }))
+                .send()
+        };
+        let old_password = login(test_support::PASSWORD).await.unwrap();
+        assert_eq!(old_password.status(), StatusCode::UNAUTHORIZED);
+        let temporary = login(temporary_password).await.unwrap();
+        assert_eq!(temporary.status(), StatusCode::OK);
+        let body: Value = temporary.json().await.unwrap();
+        assert_eq!(body["data"]["user"]["must_change_password"], true);
+    }
+
+    /// Issue a key for `owner` as an admin, returning the response's `data`
+    async fn create_api_key(app: &TestApp, owner: &TestUser, scopes: Value) -> Value {
+        let (_, admin_token) = app.signed_in("admin").await;
+        let response = app
+            .client
+            .post(app.url("/api/v1/admin/api-keys"))
+            .bearer_auth(&admin_token)
+            .json(&json!({ "user_id": owner.id, "name": "ci", "scopes": scopes }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        response.json::<Value>().await.unwrap()["data"].clone()
+    }
+
+    async fn get_with_key(app: &TestApp, path: &str, key: &str) -> reqwest::Response {
+        app.client
+            .get(app.url(path))
+            .header("x-api-key", key)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn valid_api_key_acts_as_its_owner_within_its_scopes() {
+        let app = test_support::spawn(test_support::config()).await;
+        let owner = app.user("admin").await;
+        let created = create_api_key(&app, &owner, json!(["documents:read"])).await;
+        let key = created["key"].as_str().unwrap();
+        assert!(created["api_key"].get("key_hash").is_none());
+
+        let response = get_with_key(&app, "/api/v1/auth/me", key).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["data"]["username"], owner.username);
+
+        // The owner is an admin, but the key wasn't given admin scopes
+        let response = get_with_key(&app, "/api/v1/admin/users", key).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Listings never show the key again
+        let (_, admin_token) = app.signed_in("admin").await;
+        let response = app
+            .client
+            .get(app.url(&format!("/api/v1/admin/api-keys?user_id={}", owner.id)))
+            .bearer_auth(&admin_token)
+            .send()
+            .await
+            .unwrap();
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["data"][0]["id"], created["api_key"]["id"]);
+        assert!(!body.to_string().contains(key));
+    }
+
+    #[tokio::test]
+    async fn revoked_api_key_is_rejected() {
+        let app = test_support::spawn(test_support::config()).await;
+        let owner = app.user("user").await;
+        let created = create_api_key(&app, &owner, Value::Null).await;
+        let key = created["key"].as_str().unwrap();
+        let (_, admin_token) = app.signed_in("admin").await;
+
+        let response = app
+            .client
+            .delete(app.url(&format!(
+                "/api/v1/admin/api-keys/{}",
+                created["api_key"]["id"].as_str().unwrap()
+            )))
+            .bearer_auth(&admin_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["data"]["revoked"], true);
+
+        let response = get_with_key(&app, "/api/v1/auth/me", key).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn expired_api_key_is_rejected() {
+        let app = test_support::spawn(test_support::config()).await;
+        let owner = app.user("user").await;
+        let created = create_api_key(&app, &owner, Value::Null).await;
+        let key = created["key"].as_str().unwrap();
+        assert_eq!(
+            get_with_key(&app, "/api/v1/auth/me", key).await.status(),
+            StatusCode::OK
+        );
+
+        // Keys can't be created already expired, so let this one lapse
+        sqlx::query("UPDATE api_keys SET expires_at = NOW() - INTERVAL '1 second' WHERE id = $1")
+            .bind(Uuid::parse_str(created["api_key"]["id"].as_str().unwrap()).unwrap())
+            .execute(&app.state.db)
+            .await
+            .unwrap();
+        let response = get_with_key(&app, "/api/v1/auth/me", key).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn stats_are_reported_to_admins_only() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (_, user_token) = app.signed_in("editor").await;
+        let (_, admin_token) = app.signed_in("admin").await;
+        let stats = |token: String| {
+            app.client
+                .get(app.url("/api/v1/admin/stats"))
+                .bearer_auth(token)
+                .send()
+        };
+
+        let response = stats(user_token).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Upstreams are unreachable in tests, so this also shows none is called
+        let response = stats(admin_token).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        let data = &body["data"];
+        assert!(data["uptime_secs"].is_u64());
+        for field in ["size", "idle", "in_use", "max_connections"] {
+            assert!(data["db_pool"][field].is_u64(), "db_pool.{}", field);
+        }
+        assert_eq!(data["chat_streams"], 0);
+        assert!(data.get("inflight_requests").is_some());
+        assert!(data["max_inflight_requests"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn unknown_user_is_not_found() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (_, token) = app.signed_in("admin").await;
+
+        let response = app
+            .client
+            .patch(app.url(&format!("/api/v1/admin/users/{}", Uuid::nil())))
+            .bearer_auth(&token)
+            .json(&json!({ "is_active": false }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
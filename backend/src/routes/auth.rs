@@ -1,11 +1,26 @@
-use axum::{extract::State, response::Json};
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::Json,
+    Extension,
+};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+use validator::Validate;
 
-use crate::auth::jwt;
+use crate::audit::{self, Actor, AuditEvent, ClientInfo};
+use crate::auth::error::AuthError;
+use crate::auth::jwt::TokenType;
+use crate::auth::middleware::AuthUser;
+use crate::auth::{cookie, jwt, lockout, password, refresh_tokens, revocation, upstream};
+use crate::db;
 use crate::error::AppError;
+use crate::extract::ValidatedJson;
 use crate::models::user::UserResponse;
+use crate::telemetry;
 use crate::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -14,28 +29,104 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct RegisterRequest {
+    #[validate(length(min = 3, max = 100, message = "username must be 3-100 characters"))]
+    pub username: String,
+    #[validate(email(message = "email must be a valid address"))]
+    pub email: String,
+    pub password: String,
+    #[validate(length(max = 200, message = "display_name must be at most 200 characters"))]
+    pub display_name: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RefreshRequest {
-    pub refresh_token: String,
+    /// Read from the refresh token cookie when absent
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    /// Current password, confirming the deletion
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
 }
 
 pub async fn login(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<LoginRequest>,
-) -> Result<Json<Value>, AppError> {
+    client: ClientInfo,
+    ValidatedJson(payload): ValidatedJson<LoginRequest>,
+) -> Result<(HeaderMap, Json<Value>), AppError> {
+    let config = &state.config;
+    if let Some(retry_after_secs) =
+        lockout::locked_for(&state.redis, &payload.username, config.login_max_attempts).await?
+    {
+        let actor = Actor {
+            user_id: None,
+            username: &payload.username,
+        };
+        audit::record(&state.db, &client, AuditEvent::LoginLocked, actor).await;
+        return Err(AppError::AccountLocked { retry_after_secs });
+    }
+
     let user = sqlx::query_as::<_, crate::models::user::User>(
         "SELECT * FROM users WHERE username = $1 AND is_active = true",
     )
     .bind(&payload.username)
     .fetch_optional(&state.db)
-    .await?
-    .ok_or(AppError::Unauthorized)?;
+    .await?;
 
-    let password_valid = bcrypt::verify(&payload.password, &user.password_hash)
-        .map_err(|_| AppError::Internal("Password verification failed".to_string()))?;
+    let password_valid = match &user {
+        Some(user) => bcrypt::verify(&payload.password, &user.password_hash)
+            .map_err(|_| AppError::Internal("Password verification failed".to_string()))?,
+        None => password::verify_without_account(&payload.password, config.bcrypt_cost)?,
+    };
 
-    if !password_valid {
-        return Err(AppError::Unauthorized);
+    let user = match user {
+        Some(user) if password_valid => user,
+        _ => {
+            let actor = Actor {
+                user_id: user.as_ref().map(|u| u.id),
+                username: &payload.username,
+            };
+            audit::record(&state.db, &client, AuditEvent::LoginFailed, actor).await;
+
+            let failures = lockout::record_failure(
+                &state.redis,
+                &payload.username,
+                config.login_max_attempts,
+                config.login_lockout_secs,
+            )
+            .await?;
+            if failures >= config.login_max_attempts {
+                tracing::warn!(
+                    username = %payload.username,
+                    failures,
+                    "Login locked after repeated failures"
+                );
+            }
+            return Err(AppError::Unauthorized);
+        }
+    };
+
+    lockout::reset(&state.redis, &user.username).await?;
+
+    // Upgrade hashes made before BCRYPT_COST was raised; the login succeeds regardless
+    if password::needs_rehash(&user.password_hash, config.bcrypt_cost) {
+        if let Err(e) = rehash_password(&state, user.id, &payload.password).await {
+            tracing::warn!(user = %user.username, "Failed to upgrade password hash: {}", e);
+        }
     }
 
     // Update last_login_at
@@ -48,42 +139,146 @@ pub async fn login(
         user.id,
         &user.username,
         &user.role,
-        &state.config.jwt_secret,
-        3600,
-    )
-    .map_err(|e| AppError::Internal(format!("Token creation failed: {}", e)))?;
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )?;
 
-    let refresh_token = jwt::create_refresh_token(
+    let (refresh_token, refresh_claims) = jwt::create_refresh_token(
         user.id,
         &user.username,
         &user.role,
-        &state.config.jwt_secret,
-    )
-    .map_err(|e| AppError::Internal(format!("Token creation failed: {}", e)))?;
+        &state.jwt_keys,
+        state.config.refresh_token_ttl_secs,
+    )?;
+    refresh_tokens::store(&state.db, user.id, &refresh_claims).await?;
+
+    let actor = Actor {
+        user_id: Some(user.id),
+        username: &user.username,
+    };
+    audit::record(&state.db, &client, AuditEvent::Login, actor).await;
 
     let user_resp: UserResponse = user.into();
 
-    Ok(Json(json!({
-        "success": true,
-        "data": {
-            "access_token": access_token,
-            "refresh_token": refresh_token,
-            "token_type": "Bearer",
-            "expires_in": 3600,
-            "user": user_resp
+    let mut data = json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": state.config.access_token_ttl_secs,
+        "user": user_resp
+    });
+    let headers = deliver_refresh_token(&state, refresh_token, &mut data);
+
+    Ok((headers, Json(json!({ "success": true, "data": data }))))
+}
+
+/// Hand a refresh token to the client: as an HttpOnly cookie with
+/// `refresh_token_cookie`, otherwise as `refresh_token` in `data`.
+fn deliver_refresh_token(state: &AppState, refresh_token: String, data: &mut Value) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if state.config.refresh_token_cookie {
+        headers.insert(
+            header::SET_COOKIE,
+            cookie::refresh_cookie(
+                &refresh_token,
+                state.config.refresh_token_ttl_secs,
+                state.config.refresh_cookie_secure,
+            ),
+        );
+    } else {
+        data["refresh_token"] = json!(refresh_token);
+    }
+    headers
+}
+
+/// The refresh token from the request body, falling back to the cookie when enabled.
+fn presented_refresh_token(
+    state: &AppState,
+    headers: &HeaderMap,
+    body_token: Option<String>,
+) -> Option<String> {
+    body_token.or_else(|| {
+        state
+            .config
+            .refresh_token_cookie
+            .then(|| cookie::refresh_token(headers).map(str::to_string))
+            .flatten()
+    })
+}
+
+/// Store a fresh hash of `password` at the configured cost.
+async fn rehash_password(state: &AppState, user_id: Uuid, password: &str) -> Result<(), AppError> {
+    let password_hash = password::hash(password, state.config.bcrypt_cost)?;
+    sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+        .bind(password_hash)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+    tracing::info!(user_id = %user_id, cost = state.config.bcrypt_cost, "Upgraded password hash");
+    Ok(())
+}
+
+/// POST /auth/register - Create a new account with role `user`
+pub async fn register(
+    State(state): State<Arc<AppState>>,
+    ValidatedJson(payload): ValidatedJson<RegisterRequest>,
+) -> Result<(StatusCode, Json<Value>), AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    password::check_policy(&payload.password)?;
+
+    let password_hash = password::hash(&payload.password, state.config.bcrypt_cost)?;
+
+    let user = sqlx::query_as::<_, crate::models::user::User>(
+        "INSERT INTO users (username, email, password_hash, display_name, role, is_active) \
+         VALUES ($1, $2, $3, $4, 'user', true) RETURNING *",
+    )
+    .bind(&payload.username)
+    .bind(&payload.email)
+    .bind(&password_hash)
+    .bind(&payload.display_name)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+            AppError::Validation("username or email is already registered".to_string())
         }
-    })))
+        e => AppError::Database(e),
+    })?;
+
+    tracing::info!(user = %user.username, "Registered new user");
+
+    let user_resp: UserResponse = user.into();
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "success": true,
+            "data": { "user": user_resp }
+        })),
+    ))
 }
 
 pub async fn refresh(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<RefreshRequest>,
-) -> Result<Json<Value>, AppError> {
-    let claims = jwt::verify_token(&payload.refresh_token, &state.config.jwt_secret)
-        .map_err(|_| AppError::Unauthorized)?;
+    client: ClientInfo,
+    headers: HeaderMap,
+    payload: Option<ValidatedJson<RefreshRequest>>,
+) -> Result<(HeaderMap, Json<Value>), AppError> {
+    let body_token = payload.and_then(|ValidatedJson(p)| p.refresh_token);
+    let refresh_token = presented_refresh_token(&state, &headers, body_token)
+        .ok_or_else(|| AppError::Validation("refresh_token is required".to_string()))?;
+
+    let claims = jwt::verify_token(&refresh_token, &state.jwt_keys, TokenType::Refresh)?;
+    let user_id = uuid::Uuid::parse_str(&claims.sub).map_err(|_| AuthError::Invalid)?;
+    let actor = Actor {
+        user_id: Some(user_id),
+        username: &claims.username,
+    };
 
-    let user_id =
-        uuid::Uuid::parse_str(&claims.sub).map_err(|_| AppError::Unauthorized)?;
+    if revocation::is_revoked(&state.redis, &claims.jti).await? {
+        audit::record(&state.db, &client, AuditEvent::TokenRefreshFailed, actor).await;
+        return Err(AppError::Unauthorized);
+    }
 
     let user = sqlx::query_as::<_, crate::models::user::User>(
         "SELECT * FROM users WHERE id = $1 AND is_active = true",
@@ -97,33 +292,904 @@ pub async fn refresh(
         user.id,
         &user.username,
         &user.role,
-        &state.config.jwt_secret,
-        3600,
-    )
-    .map_err(|e| AppError::Internal(format!("Token creation failed: {}", e)))?;
+        &state.jwt_keys,
+        state.config.access_token_ttl_secs,
+    )?;
 
-    let new_refresh_token = jwt::create_refresh_token(
+    let (new_refresh_token, refresh_claims) = jwt::create_refresh_token(
         user.id,
         &user.username,
         &user.role,
-        &state.config.jwt_secret,
+        &state.jwt_keys,
+        state.config.refresh_token_ttl_secs,
+    )?;
+
+    // Each refresh token is single-use. Using it up and storing its successor
+    // happen together, so a failure in between doesn't strand the session
+    let presented = claims.clone();
+    let rotated = db::transaction(&state.db, move |conn| {
+        Box::pin(async move {
+            refresh_tokens::rotate(&mut *conn, user_id, &presented).await?;
+            refresh_tokens::store(&mut *conn, user_id, &refresh_claims).await
+        })
+    })
+    .await;
+    if let Err(e) = rotated {
+        if matches!(e, AppError::Unauthorized) {
+            // Replaying a rotated token revokes the whole chain
+            refresh_tokens::revoke_if_reused(&state.db, user.id, &claims).await?;
+            audit::record(&state.db, &client, AuditEvent::TokenRefreshFailed, actor).await;
+        }
+        return Err(e);
+    }
+
+    audit::record(&state.db, &client, AuditEvent::TokenRefresh, actor).await;
+
+    let mut data = json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": state.config.access_token_ttl_secs
+    });
+    let headers = deliver_refresh_token(&state, new_refresh_token, &mut data);
+
+    Ok((headers, Json(json!({ "success": true, "data": data }))))
+}
+
+/// POST /auth/logout - Revoke the current access token (and optionally a refresh token)
+///
+/// Revoked `jti`s are kept in Redis until the token would have expired,
+/// so `auth_middleware` rejects them from then on.
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    client: ClientInfo,
+    headers: HeaderMap,
+    payload: Option<ValidatedJson<LogoutRequest>>,
+) -> Result<(HeaderMap, Json<Value>), AppError> {
+    if auth_user.api_key_id.is_some() {
+        return Err(AppError::Validation(
+            "API keys can't log out; ask an administrator to revoke the key".to_string(),
+        ));
+    }
+    revocation::revoke(&state.redis, &auth_user.token_id, auth_user.token_exp).await?;
+
+    let body_token = payload.and_then(|ValidatedJson(p)| p.refresh_token);
+    if let Some(refresh_token) = presented_refresh_token(&state, &headers, body_token) {
+        // Only revoke refresh tokens that belong to the caller
+        match jwt::verify_token(&refresh_token, &state.jwt_keys, TokenType::Refresh) {
+            Ok(claims) if claims.sub == auth_user.user_id.to_string() => {
+                revocation::revoke(&state.redis, &claims.jti, claims.exp).await?;
+            }
+            _ => tracing::warn!(
+                user = %auth_user.username,
+                "Ignoring invalid refresh token on logout"
+            ),
+        }
+    }
+
+    audit::record(&state.db, &client, AuditEvent::Logout, auth_user.actor()).await;
+
+    Ok((
+        clear_refresh_cookie(&state),
+        Json(json!({
+            "success": true,
+            "data": { "message": "Logged out successfully" }
+        })),
+    ))
+}
+
+/// POST /auth/logout-all - Revoke every session of the caller, on all devices
+///
+/// All refresh tokens are revoked, and access tokens issued before now are
+/// rejected by `auth_middleware` for the rest of their lifetime. Signing in
+/// again right away works.
+pub async fn logout_all(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    client: ClientInfo,
+) -> Result<(HeaderMap, Json<Value>), AppError> {
+    let revoked_sessions =
+        refresh_tokens::revoke_all_for_user(&state.db, auth_user.user_id).await?;
+
+    revocation::revoke_all_before_now(
+        &state.redis,
+        auth_user.user_id,
+        state.config.max_token_lifetime_secs(),
+    )
+    .await?;
+
+    tracing::info!(
+        user = %auth_user.username,
+        revoked_sessions,
+        "Logged out of all sessions"
+    );
+    audit::record(&state.db, &client, AuditEvent::LogoutAll, auth_user.actor()).await;
+
+    Ok((
+        clear_refresh_cookie(&state),
+        Json(json!({
+            "success": true,
+            "data": { "revoked_sessions": revoked_sessions }
+        })),
+    ))
+}
+
+/// Headers removing the refresh token cookie, when cookies are in use.
+fn clear_refresh_cookie(state: &AppState) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if state.config.refresh_token_cookie {
+        headers.insert(
+            header::SET_COOKIE,
+            cookie::clear_refresh_cookie(state.config.refresh_cookie_secure),
+        );
+    }
+    headers
+}
+
+/// GET /auth/me - Profile of the authenticated user
+///
+/// Requests made with an API key also get the key's `id` and `scopes` in `api_key`.
+pub async fn me(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Value>, AppError> {
+    let user = sqlx::query_as::<_, crate::models::user::User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth_user.user_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let mut data = json!(UserResponse::from(user));
+    if let Some(api_key_id) = auth_user.api_key_id {
+        data["api_key"] = json!({ "id": api_key_id, "scopes": auth_user.scopes });
+    }
+
+    Ok(Json(json!({ "success": true, "data": data })))
+}
+
+/// POST /auth/me/password - Change the caller's password
+///
+/// Clears `must_change_password` and signs out every session, this one
+/// included, so the new password has to be used to sign in again. API keys
+/// keep working.
+pub async fn change_password(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    client: ClientInfo,
+    ValidatedJson(payload): ValidatedJson<ChangePasswordRequest>,
+) -> Result<(HeaderMap, Json<Value>), AppError> {
+    let user = sqlx::query_as::<_, crate::models::user::User>(
+        "SELECT * FROM users WHERE id = $1 AND is_active = true",
+    )
+    .bind(auth_user.user_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(AppError::Unauthorized)?;
+
+    let password_valid = bcrypt::verify(&payload.current_password, &user.password_hash)
+        .map_err(|_| AppError::Internal("Password verification failed".to_string()))?;
+    if !password_valid {
+        return Err(AppError::Validation(
+            "current_password is incorrect".to_string(),
+        ));
+    }
+    if payload.new_password == payload.current_password {
+        return Err(AppError::Validation(
+            "new_password must differ from current_password".to_string(),
+        ));
+    }
+    password::check_policy(&payload.new_password)?;
+
+    let password_hash = password::hash(&payload.new_password, state.config.bcrypt_cost)?;
+    let redis = state.redis.clone();
+    let ttl_secs = state.config.max_token_lifetime_secs();
+
+    // The new password only takes effect along with the revocation of the
+    // sessions opened with the old one
+    let (user, revoked_sessions) = db::transaction(&state.db, move |conn| {
+        Box::pin(async move {
+            let user = sqlx::query_as::<_, crate::models::user::User>(
+                "UPDATE users SET password_hash = $1, must_change_password = false, \
+                 updated_at = NOW() WHERE id = $2 RETURNING *",
+            )
+            .bind(password_hash)
+            .bind(user.id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+            let revoked_sessions = refresh_tokens::revoke_all_for_user(&mut *conn, user.id).await?;
+            revocation::revoke_all_before_now(&redis, user.id, ttl_secs).await?;
+            Ok((user, revoked_sessions))
+        })
+    })
+    .await?;
+
+    tracing::info!(user = %user.username, revoked_sessions, "Password changed");
+    audit::record(
+        &state.db,
+        &client,
+        AuditEvent::PasswordChanged,
+        auth_user.actor(),
+    )
+    .await;
+
+    Ok((
+        clear_refresh_cookie(&state),
+        Json(json!({
+            "success": true,
+            "data": UserResponse::from(user)
+        })),
+    ))
+}
+
+/// DELETE /auth/me - Delete the caller's own account after confirming their password
+///
+/// The account is deactivated rather than removed, so audit history and
+/// conversations keep their owner; email and display name are erased and
+/// every session is revoked. With `purge_documents_on_account_deletion` the
+/// ETL service is also asked to delete the user's documents.
+pub async fn delete_me(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    client: ClientInfo,
+    ValidatedJson(payload): ValidatedJson<DeleteAccountRequest>,
+) -> Result<(StatusCode, HeaderMap), AppError> {
+    let user = sqlx::query_as::<_, crate::models::user::User>(
+        "SELECT * FROM users WHERE id = $1 AND is_active = true",
     )
-    .map_err(|e| AppError::Internal(format!("Token creation failed: {}", e)))?;
-
-    Ok(Json(json!({
-        "success": true,
-        "data": {
-            "access_token": access_token,
-            "refresh_token": new_refresh_token,
-            "token_type": "Bearer",
-            "expires_in": 3600
+    .bind(auth_user.user_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(AppError::Unauthorized)?;
+
+    let password_valid = bcrypt::verify(&payload.password, &user.password_hash)
+        .map_err(|_| AppError::Internal("Password verification failed".to_string()))?;
+    if !password_valid {
+        return Err(AppError::Validation("password is incorrect".to_string()));
+    }
+
+    if user.role == "admin" {
+        let other_admins: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM users WHERE role = 'admin' AND is_active = true AND id <> $1",
+        )
+        .bind(user.id)
+        .fetch_one(&state.db)
+        .await?;
+        if other_admins == 0 {
+            return Err(AppError::Validation(
+                "The last administrator account cannot be deleted".to_string(),
+            ));
         }
-    })))
+    }
+
+    sqlx::query(
+        "UPDATE users SET is_active = false, email = NULL, display_name = NULL, updated_at = NOW() \
+         WHERE id = $1",
+    )
+    .bind(user.id)
+    .execute(&state.db)
+    .await?;
+
+    let revoked_sessions = refresh_tokens::revoke_all_for_user(&state.db, user.id).await?;
+    revocation::revoke_all_before_now(
+        &state.redis,
+        user.id,
+        state.config.max_token_lifetime_secs(),
+    )
+    .await?;
+
+    tracing::info!(user = %user.username, revoked_sessions, "Account deleted by its owner");
+    audit::record(
+        &state.db,
+        &client,
+        AuditEvent::AccountDeleted,
+        auth_user.actor(),
+    )
+    .await;
+
+    if state.config.purge_documents_on_account_deletion {
+        let purge_request = state
+            .http
+            .delete(
+                state
+                    .config
+                    .etl_service_url
+                    .join(&format!("/api/v1/users/{}/documents", user.id)),
+            )
+            .headers(telemetry::trace_headers())
+            .headers(upstream::identity_headers(&state, &auth_user)?)
+            .timeout(Duration::from_millis(state.config.etl_timeout_ms));
+        tokio::spawn(purge_user_documents(purge_request, user.id));
+    }
+
+    Ok((StatusCode::NO_CONTENT, clear_refresh_cookie(&state)))
 }
 
-pub async fn logout() -> Json<Value> {
-    Json(json!({
-        "success": true,
-        "data": { "message": "Logged out successfully" }
-    }))
+/// Send the ETL request deleting the documents of `user_id`; failures are only logged.
+async fn purge_user_documents(request: reqwest::RequestBuilder, user_id: Uuid) {
+    let result = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+
+    match result {
+        Ok(_) => tracing::info!(user_id = %user_id, "Purged documents of deleted account"),
+        Err(e) => tracing::error!(
+            user_id = %user_id,
+            "Failed to purge documents of deleted account: {}",
+            e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{extract::Path, routing::delete, Json, Router};
+    use reqwest::StatusCode;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use crate::auth::jwt::{self, TokenType};
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn logged_out_access_token_is_rejected() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (_, token) = app.signed_in("user").await;
+
+        let me = |token: &str| {
+            app.client
+                .get(app.url("/api/v1/auth/me"))
+                .bearer_auth(token)
+                .send()
+        };
+        assert_eq!(me(&token).await.unwrap().status(), StatusCode::OK);
+
+        let logout = app
+            .client
+            .post(app.url("/api/v1/auth/logout"))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(logout.status(), StatusCode::OK);
+
+        assert_eq!(me(&token).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn refresh_token_is_not_an_access_token() {
+        let app = test_support::spawn(test_support::config()).await;
+        let user = app.user("user").await;
+        let login = app.login(&user).await;
+        let refresh_token = login["refresh_token"].as_str().unwrap();
+
+        let response = app
+            .client
+            .get(app.url("/api/v1/auth/me"))
+            .bearer_auth(refresh_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Nor the other way round
+        let access_token = login["access_token"].as_str().unwrap();
+        let response = app
+            .client
+            .post(app.url("/api/v1/auth/refresh"))
+            .json(&serde_json::json!({ "refresh_token": access_token }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "UNAUTHORIZED");
+    }
+
+    #[tokio::test]
+    async fn replayed_refresh_token_revokes_the_chain() {
+        let app = test_support::spawn(test_support::config()).await;
+        let user = app.user("user").await;
+        let first = app.login(&user).await["refresh_token"].clone();
+
+        let refresh = |token: &Value| {
+            app.client
+                .post(app.url("/api/v1/auth/refresh"))
+                .json(&serde_json::json!({ "refresh_token": token }))
+                .send()
+        };
+        let rotated = refresh(&first).await.unwrap();
+        assert_eq!(rotated.status(), StatusCode::OK);
+        let second = rotated.json::<Value>().await.unwrap()["data"]["refresh_token"].clone();
+
+        // Presenting the used token again is taken as theft...
+        assert_eq!(
+            refresh(&first).await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        // ...and ends the session it was rotated into
+        assert_eq!(
+            refresh(&second).await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn register_creates_a_user_once() {
+        let app = test_support::spawn(test_support::config()).await;
+        let username = format!("reg_{}", uuid::Uuid::new_v4().simple());
+        let register = |username: &str, email: &str, password: &str| {
+            app.client
+                .post(app.url("/api/v1/auth/register"))
+                .json(&serde_json::json!({
+                    "username": username,
+                    "email": email,
+                    "password": password,
+                }))
+                .send()
+        };
+        let email = format!("{}@example.com", username);
+
+        let created = register(&username, &email, test_support::PASSWORD)
+            .await
+            .unwrap();
+        assert_eq!(created.status(), StatusCode::CREATED);
+        let body: Value = created.json().await.unwrap();
+        assert_eq!(body["data"]["user"]["username"], username.as_str());
+        assert_eq!(body["data"]["user"]["role"], "user");
+
+        let other_email = format!("other_{}", email);
+        let duplicate = register(&username, &other_email, test_support::PASSWORD)
+            .await
+            .unwrap();
+        assert_eq!(duplicate.status(), StatusCode::BAD_REQUEST);
+        let body: Value = duplicate.json().await.unwrap();
+        assert_eq!(
+            body["error"]["message"],
+            "username or email is already registered"
+        );
+    }
+
+    #[tokio::test]
+    async fn register_rejects_weak_passwords() {
+        let app = test_support::spawn(test_support::config()).await;
+        let username = format!("reg_{}", uuid::Uuid::new_v4().simple());
+
+        for password in ["Sh0rt!", "alllowercaseletters"] {
+            let response = app
+                .client
+                .post(app.url("/api/v1/auth/register"))
+                .json(&serde_json::json!({
+                    "username": username,
+                    "email": format!("{}@example.com", username),
+                    "password": password,
+                }))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", password);
+            let body: Value = response.json().await.unwrap();
+            assert_eq!(body["error"]["code"], "VALIDATION_ERROR");
+        }
+    }
+
+    #[tokio::test]
+    async fn lockout_lasts_the_cooldown_from_the_locking_failure() {
+        let mut config = test_support::config();
+        config.login_max_attempts = 2;
+        config.login_lockout_secs = 2;
+        let app = test_support::spawn(config).await;
+        let user = app.user("user").await;
+        let login = |password: &str| {
+            app.client
+                .post(app.url("/api/v1/auth/login"))
+                .json(&serde_json::json!({ "username": user.username, "password": password }))
+                .send()
+        };
+        let wait = |millis| tokio::time::sleep(std::time::Duration::from_millis(millis));
+
+        assert_eq!(
+            login("wrong").await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        wait(1500).await;
+        assert_eq!(
+            login("wrong").await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // Past the first failure's window, but within the cooldown of the second
+        wait(1000).await;
+        let locked = login(test_support::PASSWORD).await.unwrap();
+        assert_eq!(locked.status(), StatusCode::FORBIDDEN);
+        let body: Value = locked.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "ACCOUNT_LOCKED");
+
+        wait(1500).await;
+        assert_eq!(
+            login(test_support::PASSWORD).await.unwrap().status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn token_lifetimes_follow_the_configured_ttls() {
+        let mut config = test_support::config();
+        config.access_token_ttl_secs = 123;
+        config.refresh_token_ttl_secs = 4567;
+        let app = test_support::spawn(config).await;
+        let user = app.user("user").await;
+        let lifetime = |token: &Value, typ| {
+            let claims =
+                jwt::verify_token(token.as_str().unwrap(), &app.state.jwt_keys, typ).unwrap();
+            claims.exp - claims.iat
+        };
+
+        let login = app.login(&user).await;
+        assert_eq!(login["expires_in"], 123);
+        assert_eq!(lifetime(&login["access_token"], TokenType::Access), 123);
+        assert_eq!(lifetime(&login["refresh_token"], TokenType::Refresh), 4567);
+
+        let refreshed: Value = app
+            .client
+            .post(app.url("/api/v1/auth/refresh"))
+            .json(&serde_json::json!({ "refresh_token": login["refresh_token"] }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let refreshed = &refreshed["data"];
+        assert_eq!(refreshed["expires_in"], 123);
+        assert_eq!(lifetime(&refreshed["access_token"], TokenType::Access), 123);
+        assert_eq!(
+            lifetime(&refreshed["refresh_token"], TokenType::Refresh),
+            4567
+        );
+    }
+
+    #[tokio::test]
+    async fn me_reflects_profile_changes_made_mid_session() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (user, token) = app.signed_in("user").await;
+        let me = || {
+            app.client
+                .get(app.url("/api/v1/auth/me"))
+                .bearer_auth(&token)
+                .send()
+        };
+
+        sqlx::query("UPDATE users SET display_name = 'Renamed by admin' WHERE id = $1")
+            .bind(user.id)
+            .execute(&app.state.db)
+            .await
+            .unwrap();
+        let body: Value = me().await.unwrap().json().await.unwrap();
+        assert_eq!(body["data"]["username"], user.username);
+        assert_eq!(body["data"]["display_name"], "Renamed by admin");
+
+        // A token whose account no longer exists is turned away
+        let orphan = jwt::create_access_token(
+            Uuid::new_v4(),
+            "deleted_user",
+            "user",
+            &app.state.jwt_keys,
+            app.state.config.access_token_ttl_secs,
+        )
+        .unwrap();
+        let response = app
+            .client
+            .get(app.url("/api/v1/auth/me"))
+            .bearer_auth(&orphan)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn login_upgrades_a_low_cost_hash() {
+        let mut config = test_support::config();
+        // Test accounts are hashed at cost 4
+        config.bcrypt_cost = 5;
+        let app = test_support::spawn(config).await;
+        let user = app.user("user").await;
+        let stored_cost = || async {
+            let hash: String = sqlx::query_scalar("SELECT password_hash FROM users WHERE id = $1")
+                .bind(user.id)
+                .fetch_one(&app.state.db)
+                .await
+                .unwrap();
+            hash.parse::<bcrypt::HashParts>().unwrap().get_cost()
+        };
+        assert_eq!(stored_cost().await, 4);
+
+        app.login(&user).await;
+        assert_eq!(stored_cost().await, 5);
+        // The upgraded hash still matches the password
+        app.login(&user).await;
+    }
+
+    #[tokio::test]
+    async fn logout_all_ends_every_session_but_allows_signing_in_again() {
+        let app = test_support::spawn(test_support::config()).await;
+        let user = app.user("user").await;
+        let first = app.login(&user).await;
+        let second = app.login(&user).await;
+        let refresh = |token: &Value| {
+            app.client
+                .post(app.url("/api/v1/auth/refresh"))
+                .json(&serde_json::json!({ "refresh_token": token }))
+                .send()
+        };
+        let me = |token: &Value| {
+            app.client
+                .get(app.url("/api/v1/auth/me"))
+                .bearer_auth(token.as_str().unwrap())
+                .send()
+        };
+
+        let response = app
+            .client
+            .post(app.url("/api/v1/auth/logout-all"))
+            .bearer_auth(first["access_token"].as_str().unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["data"]["revoked_sessions"], 2);
+
+        for session in [&first, &second] {
+            assert_eq!(
+                refresh(&session["refresh_token"]).await.unwrap().status(),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+        // Both are revoked by the cutoff, even if issued within its second
+        for session in [&first, &second] {
+            assert_eq!(
+                me(&session["access_token"]).await.unwrap().status(),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+
+        // Signing in again right after the cutoff is still accepted
+        let again = app.login(&user).await;
+        assert_eq!(
+            me(&again["access_token"]).await.unwrap().status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn refresh_token_cookie_is_set_and_accepted() {
+        let mut config = test_support::config();
+        config.refresh_token_cookie = true;
+        let app = test_support::spawn(config).await;
+        let user = app.user("user").await;
+
+        let response = app
+            .client
+            .post(app.url("/api/v1/auth/login"))
+            .json(&serde_json::json!({
+                "username": user.username,
+                "password": test_support::PASSWORD,
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let set_cookie = response.headers()[reqwest::header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        for attribute in ["Path=/api/v1/auth", "HttpOnly", "SameSite=Strict", "Secure"] {
+            assert!(set_cookie.contains(attribute), "{}", set_cookie);
+        }
+        let body: Value = response.json().await.unwrap();
+        assert!(body["data"].get("refresh_token").is_none());
+
+        // "refresh_token=<jwt>" as the browser would send it back
+        let cookie = set_cookie.split(';').next().unwrap();
+        let response = app
+            .client
+            .post(app.url("/api/v1/auth/refresh"))
+            .header(reqwest::header::COOKIE, cookie)
+            .json(&serde_json::json!({}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response
+            .headers()
+            .get(reqwest::header::SET_COOKIE)
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn refresh_token_in_the_body_still_works_with_cookies_enabled() {
+        let app = test_support::spawn(test_support::config()).await;
+        let user = app.user("user").await;
+        // Issued while cookies were off, as before switching them on
+        let refresh_token = app.login(&user).await["refresh_token"].clone();
+        assert!(refresh_token.is_string());
+
+        let mut config = test_support::config();
+        config.refresh_token_cookie = true;
+        let cookie_app = test_support::spawn(config).await;
+        let response = cookie_app
+            .client
+            .post(cookie_app.url("/api/v1/auth/refresh"))
+            .json(&serde_json::json!({ "refresh_token": refresh_token }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    async fn change_password(app: &test_support::TestApp, token: &str) -> reqwest::Response {
+        app.client
+            .post(app.url("/api/v1/auth/me/password"))
+            .bearer_auth(token)
+            .json(&serde_json::json!({
+                "current_password": test_support::PASSWORD,
+                "new_password": "Changed-password-2",
+            }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    async fn password_hash(app: &test_support::TestApp, user_id: Uuid) -> String {
+        sqlx::query_scalar("SELECT password_hash FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&app.state.db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn changing_the_password_signs_out_every_session() {
+        let app = test_support::spawn(test_support::config()).await;
+        let user = app.user("user").await;
+        let session = app.login(&user).await;
+        let token = session["access_token"].as_str().unwrap();
+
+        let response = change_password(&app, token).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let me = app
+            .client
+            .get(app.url("/api/v1/auth/me"))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(me.status(), StatusCode::UNAUTHORIZED);
+        let refresh = app
+            .client
+            .post(app.url("/api/v1/auth/refresh"))
+            .json(&serde_json::json!({ "refresh_token": session["refresh_token"] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(refresh.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn failed_session_revocation_keeps_the_old_password() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (user, token) = app.signed_in("user").await;
+        let before = password_hash(&app, user.id).await;
+
+        // Make revoking this user's refresh tokens fail after the password update
+        let function = format!("fail_revoke_{}", user.id.simple());
+        sqlx::raw_sql(&format!(
+            "CREATE FUNCTION {function}() RETURNS trigger AS $$ \
+             BEGIN RAISE EXCEPTION 'forced'; END; $$ LANGUAGE plpgsql; \
+             CREATE TRIGGER {function} BEFORE UPDATE ON refresh_tokens \
+             FOR EACH ROW WHEN (OLD.user_id = '{}') EXECUTE FUNCTION {function}();",
+            user.id
+        ))
+        .execute(&app.state.db)
+        .await
+        .unwrap();
+
+        let response = change_password(&app, &token).await;
+        sqlx::raw_sql(&format!(
+            "DROP TRIGGER {function} ON refresh_tokens; DROP FUNCTION {function}();"
+        ))
+        .execute(&app.state.db)
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(password_hash(&app, user.id).await, before);
+        app.login(&user).await;
+    }
+
+    #[tokio::test]
+    async fn account_deletion_requires_the_password() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (user, token) = app.signed_in("user").await;
+
+        let response = app
+            .client
+            .delete(app.url("/api/v1/auth/me"))
+            .bearer_auth(&token)
+            .json(&serde_json::json!({ "password": "Wrong-password-1" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let is_active: bool = sqlx::query_scalar("SELECT is_active FROM users WHERE id = $1")
+            .bind(user.id)
+            .fetch_one(&app.state.db)
+            .await
+            .unwrap();
+        assert!(is_active);
+    }
+
+    #[tokio::test]
+    async fn deleted_account_is_anonymized_signed_out_and_purged() {
+        let (purged_tx, mut purged_rx) = tokio::sync::mpsc::unbounded_channel();
+        let etl = Router::new().route(
+            "/api/v1/users/{id}/documents",
+            delete(move |Path(id): Path<Uuid>| async move {
+                purged_tx.send(id).unwrap();
+                Json(serde_json::json!({ "success": true }))
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        config.purge_documents_on_account_deletion = true;
+        let app = test_support::spawn(config).await;
+        let user = app.user("user").await;
+        let login = app.login(&user).await;
+        let token = login["access_token"].as_str().unwrap();
+
+        let response = app
+            .client
+            .delete(app.url("/api/v1/auth/me"))
+            .bearer_auth(token)
+            .json(&serde_json::json!({ "password": test_support::PASSWORD }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let stored: (bool, Option<String>, Option<String>) =
+            sqlx::query_as("SELECT is_active, email, display_name FROM users WHERE id = $1")
+                .bind(user.id)
+                .fetch_one(&app.state.db)
+                .await
+                .unwrap();
+        assert_eq!(stored, (false, None, None));
+
+        let me = app
+            .client
+            .get(app.url("/api/v1/auth/me"))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(me.status(), StatusCode::UNAUTHORIZED);
+        let refresh = app
+            .client
+            .post(app.url("/api/v1/auth/refresh"))
+            .json(&serde_json::json!({ "refresh_token": login["refresh_token"] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(refresh.status(), StatusCode::UNAUTHORIZED);
+
+        let purged = tokio::time::timeout(std::time::Duration::from_secs(5), purged_rx.recv())
+            .await
+            .unwrap();
+        assert_eq!(purged, Some(user.id));
+    }
 }
@@ -1,61 +1,282 @@
 use axum::{
-    extract::{Multipart, State},
-    response::Json,
+    body::Body,
+    extract::{multipart::Field, Multipart, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
     Extension,
 };
-use serde_json::Value;
+use bytes::Bytes;
+use futures_util::stream::{self, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use uuid::Uuid;
 
+use super::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
 use crate::auth::middleware::AuthUser;
+use crate::auth::upstream;
+use crate::batch::BatchResponse;
 use crate::error::AppError;
+use crate::extract::ValidatedJson;
+use crate::idempotency::{self, Claim};
+use crate::metrics;
+use crate::telemetry;
+use crate::upstream_body;
 use crate::AppState;
 
+/// Roles that can see documents uploaded by every user
+const VIEW_ALL_DOCUMENTS_ROLES: &[&str] = &["admin"];
+
+/// Longest accepted `Idempotency-Key` value
+const MAX_IDEMPOTENCY_KEY_CHARS: usize = 255;
+
+/// Most ids accepted by one `POST /documents/status` call
+const MAX_BATCH_STATUS_IDS: usize = 100;
+
+/// Most files uploaded by one `POST /documents/upload/batch` call
+pub const MAX_BATCH_UPLOAD_FILES: usize = 10;
+
+/// Status requests in flight to ETL at once for a batch
+const BATCH_STATUS_CONCURRENCY: usize = 8;
+
+/// Fields the document list can be sorted by; prefix with `-` for descending
+const SORTABLE_FIELDS: &[&str] = &["created_at", "file_name"];
+
+#[derive(Debug, Deserialize)]
+pub struct ListDocumentsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// e.g. `created_at` or `-file_name`
+    pub sort: Option<String>,
+}
+
 /// POST /documents/upload - Forward multipart file upload to ETL service
 ///
-/// Extracts the uploaded file from the multipart form data and
-/// re-sends it to the ETL pipeline service for processing.
+/// Extracts the uploaded file from the multipart form data and streams it
+/// to the ETL pipeline service as it arrives, without buffering it in memory.
+/// The uploader's `user_id` and `username` are sent along as form fields.
+/// A file ETL refuses with a 4xx is reported as `VALIDATION_ERROR` with ETL's reason.
+///
+/// With an `Idempotency-Key` header, a successful response is kept for
+/// `idempotency_ttl_secs` and returned again for a repeated key of the same
+/// user without contacting ETL. A repeat that arrives while the first upload
+/// is still running is rejected with `CONFLICT`; a failed upload frees the key.
 pub async fn upload_document(
     State(state): State<Arc<AppState>>,
     Extension(auth_user): Extension<AuthUser>,
-    mut multipart: Multipart,
+    headers: HeaderMap,
+    multipart: Multipart,
 ) -> Result<Json<Value>, AppError> {
-    let mut file_part: Option<(String, Vec<u8>, Option<String>)> = None;
+    let idempotency_key = match idempotency_key(&headers)? {
+        Some(key) if state.config.idempotency_ttl_secs > 0 => {
+            Some(idempotency::key("upload", auth_user.user_id, key))
+        }
+        _ => None,
+    };
+
+    let Some(key) = idempotency_key else {
+        return receive_upload(&state, &auth_user, multipart).await;
+    };
 
+    match idempotency::claim(&state.redis, &key).await {
+        Ok(Claim::Started) => {}
+        Ok(Claim::Completed(body)) => {
+            tracing::info!(
+                user = %auth_user.username,
+                "Replaying upload for repeated Idempotency-Key"
+            );
+            return Ok(Json(body));
+        }
+        Ok(Claim::InProgress) => {
+            return Err(AppError::Conflict(
+                "An upload with this Idempotency-Key is still in progress".to_string(),
+            ));
+        }
+        Err(e) => {
+            tracing::warn!("Idempotency check failed, processing upload anyway: {}", e);
+            return receive_upload(&state, &auth_user, multipart).await;
+        }
+    }
+
+    let result = receive_upload(&state, &auth_user, multipart).await;
+    let stored = match &result {
+        Ok(Json(body)) => {
+            idempotency::complete(&state.redis, &key, body, state.config.idempotency_ttl_secs).await
+        }
+        Err(_) => idempotency::release(&state.redis, &key).await,
+    };
+    if let Err(e) = stored {
+        tracing::warn!("Failed to record upload idempotency result: {}", e);
+    }
+    result
+}
+
+/// The `Idempotency-Key` header, if sent.
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<&str>, AppError> {
+    let Some(value) = headers.get(&idempotency::IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+
+    let key = value
+        .to_str()
+        .map(str::trim)
+        .map_err(|_| AppError::Validation("Idempotency-Key must be visible ASCII".to_string()))?;
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_CHARS {
+        return Err(AppError::Validation(format!(
+            "Idempotency-Key must be between 1 and {} characters",
+            MAX_IDEMPOTENCY_KEY_CHARS
+        )));
+    }
+    Ok(Some(key))
+}
+
+/// Read the multipart form and forward its `file` field.
+async fn receive_upload(
+    state: &AppState,
+    auth_user: &AuthUser,
+    mut multipart: Multipart,
+) -> Result<Json<Value>, AppError> {
     while let Some(field) = multipart.next_field().await.map_err(|e| {
         tracing::error!("Failed to read multipart field: {}", e);
         AppError::Validation(format!("Invalid multipart data: {}", e))
     })? {
-        let field_name = field.name().unwrap_or_default().to_string();
-        if field_name == "file" {
-            let file_name = field
-                .file_name()
-                .unwrap_or("unknown")
-                .to_string();
-            let content_type = field
-                .content_type()
-                .map(|ct| ct.to_string());
-            let data = field.bytes().await.map_err(|e| {
-                tracing::error!("Failed to read file bytes: {}", e);
-                AppError::Internal("Failed to read uploaded file".to_string())
-            })?;
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let (file_name, content_type) = checked_file(state, &field)?;
+        return forward_upload(state, auth_user, field, file_name, content_type).await;
+    }
+
+    Err(AppError::Validation("No file field found in upload".to_string()))
+}
 
-            file_part = Some((file_name, data.to_vec(), content_type));
-            break;
+/// POST /documents/upload/batch - Upload several files in one multipart form
+///
+/// Every `file` field is forwarded to ETL in turn, as `POST /documents/upload`
+/// would, and reported in a `BatchResponse` indexed by the field's position
+/// among the files. A rejected file doesn't stop the ones after it; fields
+/// beyond `MAX_BATCH_UPLOAD_FILES` are reported as errors without uploading.
+/// `Idempotency-Key` is not supported here.
+pub async fn upload_documents(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    mut multipart: Multipart,
+) -> Result<BatchResponse<Value>, AppError> {
+    let mut batch = BatchResponse::new();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) if batch.is_empty() => {
+                tracing::error!("Failed to read multipart field: {}", e);
+                return Err(AppError::Validation(format!(
+                    "Invalid multipart data: {}",
+                    e
+                )));
+            }
+            Err(e) => {
+                // The rest of the form is unreadable, so the upload ends here
+                tracing::error!("Failed to read multipart field: {}", e);
+                let index = batch.len();
+                batch.push(
+                    index,
+                    Err(AppError::Validation(format!(
+                        "Invalid multipart data: {}",
+                        e
+                    ))),
+                );
+                break;
+            }
+        };
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let index = batch.len();
+        if index >= MAX_BATCH_UPLOAD_FILES {
+            batch.push(
+                index,
+                Err(AppError::Validation(format!(
+                    "At most {} files can be uploaded at once",
+                    MAX_BATCH_UPLOAD_FILES
+                ))),
+            );
+            continue;
         }
+
+        let result = match checked_file(&state, &field) {
+            Ok((file_name, content_type)) => {
+                forward_upload(&state, &auth_user, field, file_name, content_type)
+                    .await
+                    .map(|Json(body)| body.get("data").cloned().unwrap_or(body))
+            }
+            Err(e) => Err(e),
+        };
+        batch.push(index, result);
     }
 
-    let (file_name, file_data, content_type) = file_part
-        .ok_or_else(|| AppError::Validation("No file field found in upload".to_string()))?;
+    if batch.is_empty() {
+        return Err(AppError::Validation(
+            "No file field found in upload".to_string(),
+        ));
+    }
+    tracing::info!(
+        user = %auth_user.username,
+        uploaded = batch.results.len(),
+        failed = batch.errors.len(),
+        "Batch upload finished"
+    );
+    Ok(batch)
+}
+
+/// File name and declared content type of an upload, once its type is allowed.
+fn checked_file(state: &AppState, field: &Field<'_>) -> Result<(String, Option<String>), AppError> {
+    let file_name = field
+        .file_name()
+        .filter(|name| !name.trim().is_empty())
+        .ok_or_else(|| AppError::Validation("Uploaded file has no filename".to_string()))?
+        .to_string();
+    let content_type = field.content_type().map(|ct| ct.to_string());
+    check_file_type(
+        &file_name,
+        content_type.as_deref(),
+        &state.config.upload_allowed_extensions,
+    )?;
+    Ok((file_name, content_type))
+}
+
+/// Chunks buffered between the client upload and the ETL request
+const UPLOAD_CHANNEL_CHUNKS: usize = 8;
+
+type UploadChunk = Result<Bytes, std::io::Error>;
 
+/// Stream a multipart file field to the ETL upload endpoint.
+async fn forward_upload(
+    state: &AppState,
+    auth_user: &AuthUser,
+    field: Field<'_>,
+    file_name: String,
+    content_type: Option<String>,
+) -> Result<Json<Value>, AppError> {
     tracing::info!(
         user = %auth_user.username,
         file = %file_name,
-        size = file_data.len(),
         "Uploading document to ETL service"
     );
 
+    // The field borrows the request body, so its chunks are relayed through a
+    // channel to the 'static stream reqwest needs
+    let (tx, rx) = mpsc::channel::<UploadChunk>(UPLOAD_CHANNEL_CHUNKS);
+    let body_stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+
     // Build multipart form for reqwest
-    let mut part = reqwest::multipart::Part::bytes(file_data)
+    let mut part = reqwest::multipart::Part::stream(reqwest::Body::wrap_stream(body_stream))
         .file_name(file_name.clone());
 
     if let Some(ct) = content_type {
@@ -69,28 +290,68 @@ pub async fn upload_document(
         });
     }
 
-    let form = reqwest::multipart::Form::new().part("file", part);
+    // Owner fields go first so ETL sees them before the file body
+    let form = reqwest::multipart::Form::new()
+        .text("user_id", auth_user.user_id.to_string())
+        .text("username", auth_user.username.clone())
+        .part("file", part);
 
-    let http_client = reqwest::Client::new();
-    let etl_response = http_client
-        .post(format!(
-            "{}/api/v1/documents/upload",
-            state.config.etl_service_url
-        ))
+    // No per-call timeout: large files can legitimately take a while to send;
+    // `request_timeout_secs` bounds the request as a whole
+    let upload_start = Instant::now();
+    let send = state
+        .http
+        .post(
+            state
+                .config
+                .etl_service_url
+                .join("/api/v1/documents/upload"),
+        )
+        .headers(telemetry::trace_headers())
+        .headers(upstream::identity_headers(state, auth_user)?)
         .multipart(form)
-        .send()
-        .await
-        .map_err(|e| {
-            tracing::error!("ETL upload request failed: {}", e);
-            AppError::Internal("Document processing service unavailable".to_string())
-        })?;
+        .send();
+    let relay = relay_field(field, tx, state.config.max_upload_bytes);
+    tokio::pin!(send, relay);
+
+    // Stop relaying once ETL has answered, since it may reject the upload early
+    let mut relayed = None;
+    let etl_response = loop {
+        tokio::select! {
+            result = &mut relay, if relayed.is_none() => relayed = Some(result),
+            response = &mut send => break response,
+        }
+    };
+    metrics::observe_upstream("etl", "upload", upload_start.elapsed());
+
+    if let Some(result) = relayed {
+        let size = result?;
+        tracing::info!(file = %file_name, size, "Relayed document to ETL service");
+    }
+
+    let etl_response = etl_response.map_err(|e| {
+        tracing::error!("ETL upload request failed: {}", e);
+        AppError::ServiceUnavailable("Document processing service unavailable".to_string())
+    })?;
 
     let status = etl_response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AppError::upstream_rate_limited(etl_response.headers()));
+    }
+    let etl_response = ensure_json(etl_response, "upload").await?;
     let body: Value = etl_response.json().await.map_err(|e| {
         tracing::error!("Failed to parse ETL upload response: {}", e);
         AppError::Internal("Invalid response from document service".to_string())
     })?;
 
+    // The file was refused, e.g. a type ETL can't parse; the caller can fix that
+    if status.is_client_error() {
+        tracing::warn!(status = %status, response = %body, "ETL service rejected upload");
+        let detail = body["detail"]
+            .as_str()
+            .unwrap_or("Document was rejected by the processing service");
+        return Err(AppError::Validation(detail.to_string()));
+    }
     if !status.is_success() {
         tracing::error!(
             status = %status,
@@ -105,27 +366,1582 @@ pub async fn upload_document(
     Ok(Json(body))
 }
 
+/// Copy the field's chunks into `tx`, returning the number of bytes relayed.
+///
+/// On failure an error is sent down the channel so the ETL request is aborted
+/// rather than completing with a truncated file.
+async fn relay_field(
+    mut field: Field<'_>,
+    tx: mpsc::Sender<UploadChunk>,
+    max_bytes: usize,
+) -> Result<usize, AppError> {
+    let mut total = 0;
+    loop {
+        let chunk = match field.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => return Ok(total),
+            Err(e) => {
+                tracing::error!("Failed to read file bytes: {}", e);
+                let _ = tx.send(Err(std::io::Error::other("client upload failed"))).await;
+                return Err(AppError::Validation(format!(
+                    "Failed to read uploaded file: {}",
+                    e
+                )));
+            }
+        };
+
+        total += chunk.len();
+        if total > max_bytes {
+            let _ = tx.send(Err(std::io::Error::other("upload too large"))).await;
+            return Err(AppError::Validation(format!(
+                "File exceeds the maximum upload size of {} bytes",
+                max_bytes
+            )));
+        }
+
+        if tx.send(Ok(chunk)).await.is_err() {
+            // ETL stopped reading the body; its response says why
+            return Ok(total);
+        }
+    }
+}
+
+/// MIME types a client may declare for each known extension.
+///
+/// `application/octet-stream` and a missing content type are treated as
+/// undeclared, since browsers send them for types they don't recognise.
+fn expected_mime_types(extension: &str) -> &'static [&'static str] {
+    match extension {
+        "pdf" => &["application/pdf"],
+        "docx" => &["application/vnd.openxmlformats-officedocument.wordprocessingml.document"],
+        "txt" => &["text/plain"],
+        "md" => &["text/markdown", "text/x-markdown", "text/plain"],
+        _ => &[],
+    }
+}
+
+/// Check the file extension against the allowlist and that the declared
+/// content type agrees with it.
+fn check_file_type(
+    file_name: &str,
+    content_type: Option<&str>,
+    allowed_extensions: &[String],
+) -> Result<(), AppError> {
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+    if !allowed_extensions.contains(&extension) {
+        return Err(AppError::Validation(format!(
+            "Unsupported file type; allowed extensions: {}",
+            allowed_extensions.join(", ")
+        )));
+    }
+
+    let mime = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_lowercase())
+        .filter(|ct| !ct.is_empty() && ct != "application/octet-stream");
+    let expected = expected_mime_types(&extension);
+    if let Some(mime) = mime {
+        if !expected.is_empty() && !expected.contains(&mime.as_str()) {
+            return Err(AppError::Validation(format!(
+                "Content type {} does not match the .{} extension",
+                mime, extension
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 /// GET /documents - List documents from ETL service
 ///
 /// Proxies the request to the ETL service and returns the document list.
+/// Users only see their own uploads unless their role can view all documents.
+/// `limit` / `offset` / `sort` are validated and passed on to ETL.
+///
+/// The response carries an `ETag`; polling clients that send it back in
+/// `If-None-Match` get `304 Not Modified` while the list is unchanged.
 pub async fn list_documents(
     State(state): State<Arc<AppState>>,
-    Extension(_auth_user): Extension<AuthUser>,
-) -> Result<Json<Value>, AppError> {
-    let http_client = reqwest::Client::new();
-    let etl_response = http_client
-        .get(format!("{}/api/v1/documents", state.config.etl_service_url))
+    Extension(auth_user): Extension<AuthUser>,
+    headers: HeaderMap,
+    Query(params): Query<ListDocumentsQuery>,
+) -> Result<Response, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+    if let Some(sort) = &params.sort {
+        let field = sort.strip_prefix('-').unwrap_or(sort);
+        if !SORTABLE_FIELDS.contains(&field) {
+            return Err(AppError::Validation(format!(
+                "sort must be one of {} (optionally prefixed with '-'): {}",
+                SORTABLE_FIELDS.join(", "),
+                sort
+            )));
+        }
+    }
+
+    let mut request = state
+        .http
+        .get(state.config.etl_service_url.join("/api/v1/documents"))
+        .headers(telemetry::trace_headers())
+        .headers(upstream::identity_headers(&state, &auth_user)?)
+        .query(&[("limit", limit), ("offset", offset)])
+        .timeout(Duration::from_millis(state.config.etl_timeout_ms));
+    if let Some(sort) = &params.sort {
+        request = request.query(&[("sort", sort)]);
+    }
+    request = request.query(&owner_filter(&auth_user));
+
+    let list_start = Instant::now();
+    let etl_response = request
         .send()
-        .await
-        .map_err(|e| {
-            tracing::error!("ETL documents list request failed: {}", e);
-            AppError::Internal("Document service unavailable".to_string())
-        })?;
+        .await;
+    metrics::observe_upstream("etl", "list_documents", list_start.elapsed());
 
+    let etl_response = etl_response.map_err(|e| {
+        tracing::error!("ETL documents list request failed: {}", e);
+        AppError::ServiceUnavailable("Document service unavailable".to_string())
+    })?;
+
+    if etl_response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AppError::upstream_rate_limited(etl_response.headers()));
+    }
+    let etl_response = ensure_json(etl_response, "list_documents").await?;
     let body: Value = etl_response.json().await.map_err(|e| {
         tracing::error!("Failed to parse ETL documents response: {}", e);
         AppError::Internal("Invalid response from document service".to_string())
     })?;
 
+    let etag = list_etag(&body);
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, LIST_CACHE_CONTROL.to_string()),
+    ];
+    if if_none_match(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+    Ok((cache_headers, Json(body)).into_response())
+}
+
+/// The list differs per user, so shared caches must not store it
+const LIST_CACHE_CONTROL: &str = "private, no-cache";
+
+/// Weak validator over the re-serialized list. Weak because the compression
+/// layer changes the bytes on the wire without touching the `ETag`.
+///
+/// `serde_json` serializes object keys in sorted order, so equal lists get equal tags.
+fn list_etag(body: &Value) -> String {
+    let digest = Sha256::digest(body.to_string());
+    format!("W/\"{:x}\"", digest)
+}
+
+/// Whether `If-None-Match` lists `etag` (or `*`), compared weakly as RFC 9110 requires.
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+/// GET /documents/{id} - Metadata and processing status of one document
+///
+/// As with the list, users only find their own uploads unless their role can
+/// view all documents; anyone else's document is `NOT_FOUND`.
+pub async fn get_document(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(document_id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    let document_id = parse_document_id(&document_id)?;
+
+    let get_start = Instant::now();
+    let etl_response = state
+        .http
+        .get(
+            state
+                .config
+                .etl_service_url
+                .join(&format!("/api/v1/documents/{}", document_id)),
+        )
+        .headers(telemetry::trace_headers())
+        .headers(upstream::identity_headers(&state, &auth_user)?)
+        .query(&owner_filter(&auth_user))
+        .timeout(Duration::from_millis(state.config.etl_timeout_ms))
+        .send()
+        .await;
+    metrics::observe_upstream("etl", "get_document", get_start.elapsed());
+
+    let etl_response = etl_response.map_err(|e| {
+        tracing::error!("ETL document request failed: {}", e);
+        AppError::ServiceUnavailable("Document service unavailable".to_string())
+    })?;
+
+    let status = etl_response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(AppError::NotFound("Document not found".to_string()));
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AppError::upstream_rate_limited(etl_response.headers()));
+    }
+    let etl_response = ensure_json(etl_response, "get_document").await?;
+    if !status.is_success() {
+        tracing::error!(status = %status, "ETL service returned error for document fetch");
+        return Err(AppError::Internal("Failed to fetch document".to_string()));
+    }
+
+    let body: Value = etl_response.json().await.map_err(|e| {
+        tracing::error!("Failed to parse ETL document response: {}", e);
+        AppError::Internal("Invalid response from document service".to_string())
+    })?;
+
+    Ok(Json(body))
+}
+
+/// GET /documents/{id}/download - The originally uploaded file, relayed from the ETL service
+///
+/// The file is streamed through as ETL sends it, without buffering it in
+/// memory. `Content-Type` and `Content-Disposition` are taken from ETL, the
+/// latter defaulting to `attachment`. `etl_timeout_ms` bounds the wait for
+/// ETL's response headers, not the transfer itself. An ETL error other than
+/// 404 or 429 is logged with its body and reported as `ServiceUnavailable`.
+/// Like the metadata, anyone else's file is `NOT_FOUND` unless the caller's
+/// role can view all documents.
+pub async fn download_document(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(document_id): Path<String>,
+) -> Result<Response, AppError> {
+    let document_id = parse_document_id(&document_id)?;
+
+    let download_start = Instant::now();
+    let request = state
+        .http
+        .get(
+            state
+                .config
+                .etl_service_url
+                .join(&format!("/api/v1/documents/{}/file", document_id)),
+        )
+        .headers(telemetry::trace_headers())
+        .headers(upstream::identity_headers(&state, &auth_user)?)
+        .query(&owner_filter(&auth_user))
+        .send();
+    let etl_response =
+        tokio::time::timeout(Duration::from_millis(state.config.etl_timeout_ms), request).await;
+    metrics::observe_upstream("etl", "download_document", download_start.elapsed());
+
+    let etl_response = match etl_response {
+        Ok(Ok(resp)) => resp,
+        Ok(Err(e)) => {
+            tracing::error!("ETL document download request failed: {}", e);
+            return Err(AppError::ServiceUnavailable(
+                "Document service unavailable".to_string(),
+            ));
+        }
+        Err(_) => {
+            tracing::error!("ETL document download request timed out");
+            return Err(AppError::ServiceUnavailable(
+                "Document service unavailable".to_string(),
+            ));
+        }
+    };
+
+    let status = etl_response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(AppError::NotFound("Document not found".to_string()));
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AppError::upstream_rate_limited(etl_response.headers()));
+    }
+    if !status.is_success() {
+        // The body is the file on success, so any error page is logged here
+        let content_type = upstream_body::content_type(&etl_response);
+        let body = upstream_body::excerpt(etl_response).await;
+        tracing::error!(
+            status = %status,
+            content_type = %content_type,
+            body = %body,
+            "ETL service returned error for document download"
+        );
+        return Err(AppError::ServiceUnavailable(
+            "Document service unavailable".to_string(),
+        ));
+    }
+
+    let upstream_header = |name: header::HeaderName, default: &'static str| {
+        etl_response
+            .headers()
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(|| header::HeaderValue::from_static(default))
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        upstream_header(header::CONTENT_TYPE, "application/octet-stream"),
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        upstream_header(header::CONTENT_DISPOSITION, "attachment"),
+    );
+    if let Some(length) = etl_response.headers().get(header::CONTENT_LENGTH) {
+        headers.insert(header::CONTENT_LENGTH, length.clone());
+    }
+
+    tracing::info!(
+        user = %auth_user.username,
+        document_id = %document_id,
+        "Downloading document from ETL service"
+    );
+
+    let body = etl_response.bytes_stream().inspect(move |chunk| {
+        if let Err(e) = chunk {
+            tracing::warn!(document_id = %document_id, "ETL document download broke off: {}", e);
+        }
+    });
+    Ok((headers, Body::from_stream(body)).into_response())
+}
+
+/// GET /documents/{id}/status - Ingestion status of one document, for polling
+///
+/// ETL's status is normalized to `{ status, progress, error? }` where `status`
+/// is one of `pending`, `processing`, `ready` or `failed` and `progress` is 0-100.
+/// Only the caller's own documents are found, as for `GET /documents/{id}`.
+pub async fn get_document_status(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(document_id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    let document_id = parse_document_id(&document_id)?;
+    let data = fetch_document_status(&state, &auth_user, document_id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchStatusRequest {
+    pub ids: Vec<String>,
+}
+
+/// POST /documents/status - Ingestion status of several documents at once
+///
+/// Returns a `BatchResponse` whose results hold the same object as
+/// `GET /documents/{id}/status`, indexed by position in `ids`. Ids that are
+/// malformed or whose lookup failed are reported in its errors instead of
+/// failing the whole request; someone else's document fails as `NOT_FOUND`.
+pub async fn batch_document_status(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    ValidatedJson(payload): ValidatedJson<BatchStatusRequest>,
+) -> Result<BatchResponse<Value>, AppError> {
+    if payload.ids.is_empty() || payload.ids.len() > MAX_BATCH_STATUS_IDS {
+        return Err(AppError::Validation(format!(
+            "ids must contain 1-{} document ids",
+            MAX_BATCH_STATUS_IDS
+        )));
+    }
+
+    // `buffered` keeps the results in request order
+    let outcomes: Vec<Result<Value, AppError>> = stream::iter(payload.ids)
+        .map(|raw_id| {
+            let state = &state;
+            let auth_user = &auth_user;
+            async move {
+                let document_id = parse_document_id(&raw_id)?;
+                fetch_document_status(state, auth_user, document_id).await
+            }
+        })
+        .buffered(BATCH_STATUS_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut batch = BatchResponse::new();
+    for (index, result) in outcomes.into_iter().enumerate() {
+        batch.push(index, result);
+    }
+    Ok(batch)
+}
+
+/// Normalized ingestion status of one document, fetched from ETL.
+async fn fetch_document_status(
+    state: &AppState,
+    auth_user: &AuthUser,
+    document_id: Uuid,
+) -> Result<Value, AppError> {
+    let status_start = Instant::now();
+    let etl_response = state
+        .http
+        .get(
+            state
+                .config
+                .etl_service_url
+                .join(&format!("/api/v1/documents/{}/status", document_id)),
+        )
+        .headers(telemetry::trace_headers())
+        .headers(upstream::identity_headers(state, auth_user)?)
+        .query(&owner_filter(auth_user))
+        .timeout(Duration::from_millis(state.config.etl_timeout_ms))
+        .send()
+        .await;
+    metrics::observe_upstream("etl", "document_status", status_start.elapsed());
+
+    let etl_response = etl_response.map_err(|e| {
+        tracing::error!("ETL document status request failed: {}", e);
+        AppError::ServiceUnavailable("Document service unavailable".to_string())
+    })?;
+
+    let status = etl_response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(AppError::NotFound("Document not found".to_string()));
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AppError::upstream_rate_limited(etl_response.headers()));
+    }
+    let etl_response = ensure_json(etl_response, "document_status").await?;
+    if !status.is_success() {
+        tracing::error!(status = %status, "ETL service returned error for document status");
+        return Err(AppError::Internal(
+            "Failed to fetch document status".to_string(),
+        ));
+    }
+
+    let body: Value = etl_response.json().await.map_err(|e| {
+        tracing::error!("Failed to parse ETL document status response: {}", e);
+        AppError::Internal("Invalid response from document service".to_string())
+    })?;
+
+    let mut data = normalize_status(body.get("data").unwrap_or(&body));
+    data["document_id"] = json!(document_id);
+    Ok(data)
+}
+
+/// Map an ETL status payload onto the gateway's fixed status vocabulary.
+///
+/// Unrecognized statuses are reported as `processing` so clients keep polling.
+fn normalize_status(etl: &Value) -> Value {
+    let raw_status = etl
+        .get("status")
+        .or_else(|| etl.get("etl_status"))
+        .and_then(|s| s.as_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let status = match raw_status.as_str() {
+        "pending" | "queued" | "uploaded" => "pending",
+        "processing" | "running" | "in_progress" => "processing",
+        "ready" | "completed" | "complete" | "done" => "ready",
+        "failed" | "error" => "failed",
+        other => {
+            tracing::warn!(status = %other, "Unknown ETL document status");
+            "processing"
+        }
+    };
+
+    let progress = match status {
+        "pending" => 0,
+        "ready" => 100,
+        _ => etl
+            .get("progress")
+            .and_then(|p| p.as_f64())
+            .map(|p| p.clamp(0.0, 100.0).round() as u8)
+            .unwrap_or(0),
+    };
+
+    let mut normalized = json!({ "status": status, "progress": progress });
+    if status == "failed" {
+        let error = etl
+            .get("error")
+            .or_else(|| etl.get("etl_error"))
+            .and_then(|e| e.as_str())
+            .unwrap_or("Document processing failed");
+        normalized["error"] = json!(error);
+    }
+    normalized
+}
+
+/// DELETE /documents/{id} - Remove a document via the ETL service
+///
+/// Editors can only remove their own uploads; admins can remove any.
+pub async fn delete_document(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(document_id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    let document_id = parse_document_id(&document_id)?;
+
+    tracing::info!(
+        user = %auth_user.username,
+        document_id = %document_id,
+        "Deleting document via ETL service"
+    );
+
+    let delete_start = Instant::now();
+    let etl_response = state
+        .http
+        .delete(
+            state
+                .config
+                .etl_service_url
+                .join(&format!("/api/v1/documents/{}", document_id)),
+        )
+        .headers(telemetry::trace_headers())
+        .headers(upstream::identity_headers(&state, &auth_user)?)
+        .query(&owner_filter(&auth_user))
+        .timeout(Duration::from_millis(state.config.etl_timeout_ms))
+        .send()
+        .await;
+    metrics::observe_upstream("etl", "delete_document", delete_start.elapsed());
+
+    let etl_response = etl_response.map_err(|e| {
+        tracing::error!("ETL document delete request failed: {}", e);
+        AppError::ServiceUnavailable("Document service unavailable".to_string())
+    })?;
+
+    let status = etl_response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(AppError::NotFound("Document not found".to_string()));
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AppError::upstream_rate_limited(etl_response.headers()));
+    }
+    let etl_response = ensure_json(etl_response, "delete_document").await?;
+    if !status.is_success() {
+        tracing::error!(status = %status, "ETL service returned error for delete");
+        return Err(AppError::Internal("Document deletion failed".to_string()));
+    }
+
+    let body = etl_response.bytes().await.map_err(|e| {
+        tracing::error!("Failed to read ETL delete response: {}", e);
+        AppError::Internal("Invalid response from document service".to_string())
+    })?;
+
+    // A 204 from ETL has no body to relay
+    if body.is_empty() {
+        return Ok(Json(json!({
+            "success": true,
+            "data": { "document_id": document_id }
+        })));
+    }
+
+    let body: Value = serde_json::from_slice(&body).map_err(|e| {
+        tracing::error!("Failed to parse ETL delete response: {}", e);
+        AppError::Internal("Invalid response from document service".to_string())
+    })?;
+
+    Ok(Json(body))
+}
+
+/// POST /documents/{id}/reprocess - Re-run ingestion of a stored document via the ETL service
+///
+/// The ETL service re-parses the already uploaded file, so improvements to
+/// the pipeline can be applied without uploading again. A 409 from ETL, e.g.
+/// while the document is still being processed, is passed on as `CONFLICT`.
+/// Editors can only reprocess their own uploads.
+pub async fn reprocess_document(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(document_id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    let document_id = parse_document_id(&document_id)?;
+
+    tracing::info!(
+        user = %auth_user.username,
+        document_id = %document_id,
+        "Reprocessing document via ETL service"
+    );
+
+    let reprocess_start = Instant::now();
+    let etl_response = state
+        .http
+        .post(
+            state
+                .config
+                .etl_service_url
+                .join(&format!("/api/v1/documents/{}/reprocess", document_id)),
+        )
+        .headers(telemetry::trace_headers())
+        .headers(upstream::identity_headers(&state, &auth_user)?)
+        .query(&owner_filter(&auth_user))
+        .timeout(Duration::from_millis(state.config.etl_timeout_ms))
+        .send()
+        .await;
+    metrics::observe_upstream("etl", "reprocess_document", reprocess_start.elapsed());
+
+    let etl_response = etl_response.map_err(|e| {
+        tracing::error!("ETL document reprocess request failed: {}", e);
+        AppError::ServiceUnavailable("Document service unavailable".to_string())
+    })?;
+
+    let status = etl_response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(AppError::NotFound("Document not found".to_string()));
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(AppError::upstream_rate_limited(etl_response.headers()));
+    }
+    if status == reqwest::StatusCode::CONFLICT {
+        return Err(AppError::Conflict(
+            "Document is already being processed".to_string(),
+        ));
+    }
+    let etl_response = ensure_json(etl_response, "reprocess_document").await?;
+    if !status.is_success() {
+        tracing::error!(status = %status, "ETL service returned error for reprocess");
+        return Err(AppError::Internal(
+            "Document reprocessing failed".to_string(),
+        ));
+    }
+
+    let body: Value = etl_response.json().await.map_err(|e| {
+        tracing::error!("Failed to parse ETL reprocess response: {}", e);
+        AppError::Internal("Invalid response from document service".to_string())
+    })?;
+
     Ok(Json(body))
 }
+
+/// Reject an ETL response that isn't JSON, such as the HTML or plain-text
+/// page a proxy in front of ETL serves for a 502.
+///
+/// Such a page means ETL itself didn't answer, so its body is logged and the
+/// call reported as `ServiceUnavailable` rather than as an invalid response.
+/// Responses without a body pass.
+async fn ensure_json(
+    resp: reqwest::Response,
+    operation: &'static str,
+) -> Result<reqwest::Response, AppError> {
+    let empty =
+        resp.status() == reqwest::StatusCode::NO_CONTENT || resp.content_length() == Some(0);
+    if empty || upstream_body::is_json(&resp) {
+        return Ok(resp);
+    }
+
+    let status = resp.status();
+    let content_type = upstream_body::content_type(&resp);
+    let body = upstream_body::excerpt(resp).await;
+    tracing::error!(
+        operation,
+        status = %status,
+        content_type = %content_type,
+        body = %body,
+        "ETL service returned a non-JSON response"
+    );
+    Err(AppError::ServiceUnavailable(
+        "Document service unavailable".to_string(),
+    ))
+}
+
+/// ETL query limiting documents to those the caller uploaded, empty for
+/// roles in `VIEW_ALL_DOCUMENTS_ROLES`.
+///
+/// ETL answers 404 for someone else's document, as if it didn't exist.
+fn owner_filter(auth_user: &AuthUser) -> Vec<(&'static str, String)> {
+    if VIEW_ALL_DOCUMENTS_ROLES.contains(&auth_user.role.as_str()) {
+        return Vec::new();
+    }
+    vec![("user_id", auth_user.user_id.to_string())]
+}
+
+/// Reject malformed ids before making any upstream call.
+fn parse_document_id(raw: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(raw).map_err(|_| AppError::Validation("document id must be a UUID".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        extract::{Multipart, Path, Query, State},
+        routing::{delete, get, post},
+        Json, Router,
+    };
+    use reqwest::StatusCode;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::test_support::{self, TestApp};
+
+    /// ETL stand-in accepting uploads and reporting the file size it received
+    fn etl_upload() -> Router {
+        Router::new().route(
+            "/api/v1/documents/upload",
+            post(|mut form: Multipart| async move {
+                let mut size = 0;
+                while let Some(field) = form.next_field().await.unwrap() {
+                    if field.name() == Some("file") {
+                        size = field.bytes().await.unwrap().len();
+                    }
+                }
+                (
+                    axum::http::StatusCode::ACCEPTED,
+                    Json(json!({ "success": true, "data": { "size": size } })),
+                )
+            }),
+        )
+    }
+
+    async fn upload(
+        app: &TestApp,
+        token: &str,
+        file_name: &str,
+        content_type: &str,
+        contents: Vec<u8>,
+    ) -> reqwest::Response {
+        let part = reqwest::multipart::Part::bytes(contents)
+            .file_name(file_name.to_string())
+            .mime_str(content_type)
+            .unwrap();
+        app.client
+            .post(app.url("/api/v1/documents/upload"))
+            .bearer_auth(token)
+            .multipart(reqwest::multipart::Form::new().part("file", part))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn upload_is_limited_to_max_upload_bytes() {
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl_upload()).await;
+        config.max_upload_bytes = 1024;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+
+        let at_limit = upload(&app, &token, "a.txt", "text/plain", vec![b'a'; 1024]).await;
+        assert_eq!(at_limit.status(), StatusCode::OK);
+        let body: Value = at_limit.json().await.unwrap();
+        assert_eq!(body["data"]["size"], 1024);
+
+        let over_limit = upload(&app, &token, "a.txt", "text/plain", vec![b'a'; 1025]).await;
+        assert_eq!(over_limit.status(), StatusCode::BAD_REQUEST);
+        let body: Value = over_limit.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "VALIDATION_ERROR");
+    }
+
+    #[tokio::test]
+    async fn only_allowed_file_types_are_uploaded() {
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl_upload()).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+        let contents = || b"# Notes".to_vec();
+
+        let allowed = upload(&app, &token, "notes.md", "text/markdown", contents()).await;
+        assert_eq!(allowed.status(), StatusCode::OK);
+
+        for (file_name, content_type) in [
+            ("setup.exe", "application/octet-stream"),
+            ("notes.pdf", "text/markdown"),
+        ] {
+            let rejected = upload(&app, &token, file_name, content_type, contents()).await;
+            assert_eq!(rejected.status(), StatusCode::BAD_REQUEST, "{}", file_name);
+            let body: Value = rejected.json().await.unwrap();
+            assert_eq!(body["error"]["code"], "VALIDATION_ERROR");
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_upload_reports_the_rejected_file_by_index() {
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl_upload()).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+        let part = |file_name: &str, content_type: &str| {
+            reqwest::multipart::Part::bytes(b"# Notes".to_vec())
+                .file_name(file_name.to_string())
+                .mime_str(content_type)
+                .unwrap()
+        };
+        let form = reqwest::multipart::Form::new()
+            .part("file", part("notes.md", "text/markdown"))
+            .part("file", part("setup.exe", "application/octet-stream"))
+            .part("file", part("more.md", "text/markdown"));
+
+        let response = app
+            .client
+            .post(app.url("/api/v1/documents/upload/batch"))
+            .bearer_auth(&token)
+            .multipart(form)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        let body: Value = response.json().await.unwrap();
+        let indexes = |items: &Value| -> Vec<u64> {
+            items
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["index"].as_u64().unwrap())
+                .collect()
+        };
+        assert_eq!(indexes(&body["data"]["results"]), [0, 2]);
+        assert_eq!(indexes(&body["data"]["errors"]), [1]);
+        assert_eq!(body["data"]["errors"][0]["code"], "VALIDATION_ERROR");
+    }
+
+    #[tokio::test]
+    async fn file_refused_by_etl_is_a_validation_error() {
+        let etl = Router::new().route(
+            "/api/v1/documents/upload",
+            post(|| async {
+                (
+                    axum::http::StatusCode::BAD_REQUEST,
+                    Json(json!({ "detail": "Unsupported file type: .md" })),
+                )
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+
+        let contents = b"# Notes".to_vec();
+        let response = upload(&app, &token, "notes.md", "text/markdown", contents).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "VALIDATION_ERROR");
+        assert_eq!(body["error"]["message"], "Unsupported file type: .md");
+    }
+
+    #[tokio::test]
+    async fn upload_reaches_etl_before_the_client_finishes_sending() {
+        let (first_bytes_tx, first_bytes_rx) = tokio::sync::oneshot::channel::<()>();
+        let first_bytes_tx = Arc::new(Mutex::new(Some(first_bytes_tx)));
+        let etl = Router::new().route(
+            "/api/v1/documents/upload",
+            post(move |mut form: Multipart| async move {
+                let mut size = 0;
+                while let Some(mut field) = form.next_field().await.unwrap() {
+                    if field.name() != Some("file") {
+                        continue;
+                    }
+                    while let Some(chunk) = field.chunk().await.unwrap() {
+                        size += chunk.len();
+                        if let Some(tx) = first_bytes_tx.lock().unwrap().take() {
+                            let _ = tx.send(());
+                        }
+                    }
+                }
+                Json(json!({ "success": true, "data": { "size": size } }))
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+
+        // The second half is only sent once ETL has seen the first, which
+        // can't happen if the gateway buffers the whole file
+        let half = vec![b'a'; 512 * 1024];
+        let streamed = Arc::new(AtomicBool::new(false));
+        let body = {
+            let streamed = streamed.clone();
+            async_stream::stream! {
+                yield Ok::<_, std::io::Error>(half.clone());
+                let reached = tokio::time::timeout(Duration::from_secs(5), first_bytes_rx).await;
+                streamed.store(reached.is_ok(), Ordering::SeqCst);
+                yield Ok(half);
+            }
+        };
+        let part = reqwest::multipart::Part::stream(reqwest::Body::wrap_stream(body))
+            .file_name("large.txt")
+            .mime_str("text/plain")
+            .unwrap();
+        let resp = app
+            .client
+            .post(app.url("/api/v1/documents/upload"))
+            .bearer_auth(&token)
+            .multipart(reqwest::multipart::Form::new().part("file", part))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = resp.json().await.unwrap();
+        assert_eq!(body["data"]["size"], 1024 * 1024);
+        assert!(streamed.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn documents_are_scoped_to_their_uploader() {
+        let etl = Router::new()
+            .route(
+                "/api/v1/documents/upload",
+                post(|mut form: Multipart| async move {
+                    let mut fields = HashMap::new();
+                    while let Some(field) = form.next_field().await.unwrap() {
+                        let name = field.name().unwrap().to_string();
+                        if name != "file" {
+                            fields.insert(name, field.text().await.unwrap());
+                        }
+                    }
+                    Json(json!({ "success": true, "data": fields }))
+                }),
+            )
+            .route(
+                "/api/v1/documents",
+                get(|Query(params): Query<HashMap<String, String>>| async move {
+                    Json(json!({ "success": true, "data": [], "meta": params }))
+                }),
+            );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (editor, editor_token) = app.signed_in("editor").await;
+        let (_, admin_token) = app.signed_in("admin").await;
+
+        let uploaded = upload(&app, &editor_token, "a.txt", "text/plain", b"a".to_vec()).await;
+        let body: Value = uploaded.json().await.unwrap();
+        assert_eq!(body["data"]["user_id"], editor.id.to_string());
+        assert_eq!(body["data"]["username"], editor.username);
+
+        let list = |token: &str| {
+            let request = app
+                .client
+                .get(app.url("/api/v1/documents"))
+                .bearer_auth(token);
+            async move { request.send().await.unwrap().json::<Value>().await.unwrap() }
+        };
+        let own = list(&editor_token).await;
+        assert_eq!(own["meta"]["user_id"], editor.id.to_string());
+        let all = list(&admin_token).await;
+        assert!(all["meta"].get("user_id").is_none(), "{}", all);
+    }
+
+    /// A document the ETL stand-ins know about; any other id is unknown to them
+    const KNOWN_ID: &str = "7f6f6d5c-7a35-4d8e-9a7b-0d6c3f0e2a11";
+    const UNKNOWN_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+    /// `404` from ETL for any document but `KNOWN_ID`
+    fn unknown_document() -> (axum::http::StatusCode, Json<Value>) {
+        (
+            axum::http::StatusCode::NOT_FOUND,
+            Json(json!({ "detail": "Document not found" })),
+        )
+    }
+
+    #[tokio::test]
+    async fn delete_is_relayed_to_etl() {
+        let etl = Router::new().route(
+            "/api/v1/documents/{id}",
+            delete(|Path(id): Path<String>| async move {
+                if id != KNOWN_ID {
+                    return unknown_document();
+                }
+                (
+                    axum::http::StatusCode::OK,
+                    Json(json!({
+                        "success": true,
+                        "data": { "document_id": id, "deleted": true },
+                    })),
+                )
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+        let delete = |id: &str| {
+            app.client
+                .delete(app.url(&format!("/api/v1/documents/{}", id)))
+                .bearer_auth(&token)
+                .send()
+        };
+
+        let deleted = delete(KNOWN_ID).await.unwrap();
+        assert_eq!(deleted.status(), StatusCode::OK);
+        let body: Value = deleted.json().await.unwrap();
+        assert_eq!(body["data"]["deleted"], true);
+
+        let missing = delete(UNKNOWN_ID).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+        let body: Value = missing.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn malformed_document_id_is_rejected_before_calling_etl() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let etl = Router::new().route(
+            "/api/v1/documents/{id}",
+            get({
+                let calls = calls.clone();
+                move |Path(id): Path<String>| async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    if id != KNOWN_ID {
+                        return unknown_document();
+                    }
+                    (
+                        axum::http::StatusCode::OK,
+                        Json(json!({ "success": true, "data": { "id": id } })),
+                    )
+                }
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("user").await;
+        let get = |id: &str| {
+            app.client
+                .get(app.url(&format!("/api/v1/documents/{}", id)))
+                .bearer_auth(&token)
+                .send()
+        };
+
+        let malformed = get("not-a-uuid").await.unwrap();
+        assert_eq!(malformed.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let found = get(KNOWN_ID).await.unwrap();
+        assert_eq!(found.status(), StatusCode::OK);
+        let body: Value = found.json().await.unwrap();
+        assert_eq!(body["data"]["id"], KNOWN_ID);
+        assert_eq!(
+            get(UNKNOWN_ID).await.unwrap().status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    /// ETL stand-in listing no documents and counting the list requests it received
+    fn etl_list(calls: Arc<AtomicUsize>) -> Router {
+        Router::new().route(
+            "/api/v1/documents",
+            get(
+                move |Query(params): Query<HashMap<String, String>>| async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Json(json!({ "success": true, "data": [], "meta": params }))
+                },
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn list_params_are_forwarded_to_etl() {
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl_list(Arc::default())).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("admin").await;
+        let list = |query: &'static [(&'static str, &'static str)]| {
+            let request = app
+                .client
+                .get(app.url("/api/v1/documents"))
+                .query(query)
+                .bearer_auth(&token);
+            async move { request.send().await.unwrap().json::<Value>().await.unwrap() }
+        };
+
+        let forwarded = list(&[("limit", "5"), ("offset", "10"), ("sort", "-file_name")]).await;
+        assert_eq!(
+            forwarded["meta"],
+            json!({ "limit": "5", "offset": "10", "sort": "-file_name" })
+        );
+
+        // Out-of-range paging is clamped rather than passed on
+        let clamped = list(&[("limit", "1000"), ("offset", "-3")]).await;
+        assert_eq!(clamped["meta"], json!({ "limit": "100", "offset": "0" }));
+    }
+
+    #[tokio::test]
+    async fn unknown_sort_field_is_rejected_before_calling_etl() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl_list(calls.clone())).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("admin").await;
+
+        let response = app
+            .client
+            .get(app.url("/api/v1/documents"))
+            .query(&[("sort", "password_hash")])
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "VALIDATION_ERROR");
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn unchanged_list_is_not_modified() {
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl_list(Arc::default())).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("admin").await;
+        let list = |limit: &'static str, if_none_match: Option<String>| {
+            let mut request = app
+                .client
+                .get(app.url("/api/v1/documents"))
+                .query(&[("limit", limit)])
+                .bearer_auth(&token);
+            if let Some(etag) = if_none_match {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            async move { request.send().await.unwrap() }
+        };
+
+        let first = list("5", None).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        let etag = first.headers()[reqwest::header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let unchanged = list("5", Some(etag.clone())).await;
+        assert_eq!(unchanged.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(unchanged.headers()[reqwest::header::ETAG], etag.as_str());
+        assert!(unchanged.bytes().await.unwrap().is_empty());
+
+        // A different list under the old tag is sent in full
+        let changed = list("6", Some(etag)).await;
+        assert_eq!(changed.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn etl_statuses_are_normalized() {
+        // ETL payload and the normalized status reported for it
+        let cases = [
+            (
+                json!({ "status": "queued" }),
+                json!({ "status": "pending", "progress": 0 }),
+            ),
+            (
+                json!({ "status": "processing", "progress": 41.6 }),
+                json!({ "status": "processing", "progress": 42 }),
+            ),
+            (
+                json!({ "etl_status": "completed" }),
+                json!({ "status": "ready", "progress": 100 }),
+            ),
+            (
+                json!({ "etl_status": "failed", "etl_error": "Unreadable PDF" }),
+                json!({ "status": "failed", "progress": 0, "error": "Unreadable PDF" }),
+            ),
+            (
+                json!({ "status": "indexing" }),
+                json!({ "status": "processing", "progress": 0 }),
+            ),
+        ];
+        let ids: Vec<String> = (0..cases.len())
+            .map(|_| uuid::Uuid::new_v4().to_string())
+            .collect();
+        let payloads: HashMap<String, Value> = ids
+            .iter()
+            .cloned()
+            .zip(cases.iter().map(|(etl, _)| etl.clone()))
+            .collect();
+        let etl = Router::new().route(
+            "/api/v1/documents/{id}/status",
+            get(move |Path(id): Path<String>| async move {
+                match payloads.get(&id) {
+                    Some(payload) => (
+                        StatusCode::OK,
+                        Json(json!({ "success": true, "data": payload })),
+                    ),
+                    None => unknown_document(),
+                }
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("user").await;
+        let status = |id: &str| {
+            app.client
+                .get(app.url(&format!("/api/v1/documents/{}/status", id)))
+                .bearer_auth(&token)
+                .send()
+        };
+
+        for (id, (_, expected)) in ids.iter().zip(&cases) {
+            let body: Value = status(id).await.unwrap().json().await.unwrap();
+            let mut expected = expected.clone();
+            expected["document_id"] = json!(id);
+            assert_eq!(body["data"], expected);
+        }
+
+        let missing = status(UNKNOWN_ID).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Uploader of `KNOWN_ID` in `etl_owned`
+    type Owner = Arc<Mutex<String>>;
+
+    /// `KNOWN_ID` as ETL reports it: missing when it doesn't exist or when the
+    /// `user_id` filter names someone other than its uploader
+    async fn owned_document(
+        State(owner): State<Owner>,
+        Path(id): Path<String>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> (axum::http::StatusCode, Json<Value>) {
+        let foreign = params
+            .get("user_id")
+            .is_some_and(|user_id| *user_id != *owner.lock().unwrap());
+        if id != KNOWN_ID || foreign {
+            return unknown_document();
+        }
+        (
+            axum::http::StatusCode::OK,
+            Json(json!({ "success": true, "data": { "id": id, "status": "completed" } })),
+        )
+    }
+
+    /// ETL stand-in for the single-document endpoints, holding `KNOWN_ID` uploaded by `owner`
+    fn etl_owned(owner: Owner) -> Router {
+        Router::new()
+            .route(
+                "/api/v1/documents/{id}",
+                get(owned_document).delete(owned_document),
+            )
+            .route("/api/v1/documents/{id}/status", get(owned_document))
+            .route("/api/v1/documents/{id}/file", get(owned_document))
+            .route("/api/v1/documents/{id}/reprocess", post(owned_document))
+            .with_state(owner)
+    }
+
+    #[tokio::test]
+    async fn other_users_documents_are_not_found() {
+        let owner = Owner::default();
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl_owned(owner.clone())).await;
+        let app = test_support::spawn(config).await;
+        let (uploader, uploader_token) = app.signed_in("editor").await;
+        let (_, other_token) = app.signed_in("editor").await;
+        let (_, admin_token) = app.signed_in("admin").await;
+        *owner.lock().unwrap() = uploader.id.to_string();
+
+        let document = format!("/api/v1/documents/{}", KNOWN_ID);
+        let status = format!("{}/status", document);
+        let download = format!("{}/download", document);
+        let reprocess = format!("{}/reprocess", document);
+        let send = |method: reqwest::Method, path: &str, token: &str| {
+            app.client
+                .request(method, app.url(path))
+                .bearer_auth(token)
+                .send()
+        };
+        for token in [&uploader_token, &other_token, &admin_token] {
+            let expected = if token == &other_token {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::OK
+            };
+            for (method, path) in [
+                (reqwest::Method::GET, &document),
+                (reqwest::Method::GET, &status),
+                (reqwest::Method::GET, &download),
+                (reqwest::Method::POST, &reprocess),
+                (reqwest::Method::DELETE, &document),
+            ] {
+                let response = send(method.clone(), path, token).await.unwrap();
+                assert_eq!(response.status(), expected, "{} {}", method, path);
+            }
+        }
+
+        let response = app
+            .client
+            .post(app.url("/api/v1/documents/status"))
+            .bearer_auth(&other_token)
+            .json(&json!({ "ids": [KNOWN_ID] }))
+            .send()
+            .await
+            .unwrap();
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["data"]["errors"][0]["code"], "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn batch_status_reports_bad_ids_per_item() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let etl = Router::new().route(
+            "/api/v1/documents/{id}/status",
+            get({
+                let calls = calls.clone();
+                move |Path(id): Path<String>| async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    if id != KNOWN_ID {
+                        return unknown_document();
+                    }
+                    (
+                        axum::http::StatusCode::OK,
+                        Json(json!({ "success": true, "data": { "status": "completed" } })),
+                    )
+                }
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("user").await;
+
+        let response = app
+            .client
+            .post(app.url("/api/v1/documents/status"))
+            .bearer_auth(&token)
+            .json(&json!({ "ids": [KNOWN_ID, "not-a-uuid", UNKNOWN_ID] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        let body: Value = response.json().await.unwrap();
+        let results = body["data"]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["index"], 0);
+        assert_eq!(results[0]["data"]["status"], "ready");
+        assert_eq!(results[0]["data"]["document_id"], KNOWN_ID);
+        let errors: Vec<(u64, &str)> = body["data"]["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| (e["index"].as_u64().unwrap(), e["code"].as_str().unwrap()))
+            .collect();
+        assert_eq!(errors, vec![(1, "VALIDATION_ERROR"), (2, "NOT_FOUND")]);
+        // The malformed id never reaches ETL
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn reprocess_is_relayed_to_etl() {
+        let etl = Router::new().route(
+            "/api/v1/documents/{id}/reprocess",
+            post(|Path(id): Path<String>| async move {
+                if id != KNOWN_ID {
+                    return unknown_document();
+                }
+                (
+                    axum::http::StatusCode::OK,
+                    Json(json!({
+                        "success": true,
+                        "data": { "document_id": id, "status": "completed", "chunk_count": 3 },
+                    })),
+                )
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, editor_token) = app.signed_in("editor").await;
+        let (_, user_token) = app.signed_in("user").await;
+        let reprocess = |id: &str, token: &str| {
+            app.client
+                .post(app.url(&format!("/api/v1/documents/{}/reprocess", id)))
+                .bearer_auth(token)
+                .send()
+        };
+
+        let done = reprocess(KNOWN_ID, &editor_token).await.unwrap();
+        assert_eq!(done.status(), StatusCode::OK);
+        let body: Value = done.json().await.unwrap();
+        assert_eq!(body["data"]["chunk_count"], 3);
+
+        let missing = reprocess(UNKNOWN_ID, &editor_token).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+        let malformed = reprocess("not-a-uuid", &editor_token).await.unwrap();
+        assert_eq!(malformed.status(), StatusCode::BAD_REQUEST);
+        let forbidden = reprocess(KNOWN_ID, &user_token).await.unwrap();
+        assert_eq!(forbidden.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn etl_rate_limit_is_relayed_with_its_retry_after() {
+        let busy = || async {
+            (
+                axum::http::StatusCode::TOO_MANY_REQUESTS,
+                [(axum::http::header::RETRY_AFTER, "30")],
+                Json(json!({ "detail": "Too many requests" })),
+            )
+        };
+        let etl = Router::new()
+            .route("/api/v1/documents", get(busy))
+            .route("/api/v1/documents/{id}/status", get(busy));
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+
+        for path in [
+            "/api/v1/documents".to_string(),
+            format!("/api/v1/documents/{}/status", KNOWN_ID),
+        ] {
+            let response = app
+                .client
+                .get(app.url(&path))
+                .bearer_auth(&token)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS, "{}", path);
+            assert_eq!(response.headers()["retry-after"], "30", "{}", path);
+            let body: Value = response.json().await.unwrap();
+            assert_eq!(body["error"]["code"], "RATE_LIMITED", "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn download_streams_the_file_with_its_headers() {
+        use axum::response::IntoResponse;
+
+        let (first_bytes_tx, first_bytes_rx) = tokio::sync::oneshot::channel::<()>();
+        let first_bytes_rx = Arc::new(Mutex::new(Some(first_bytes_rx)));
+        let etl = Router::new().route(
+            "/api/v1/documents/{id}/file",
+            get(move |Path(id): Path<String>| async move {
+                if id != KNOWN_ID {
+                    return unknown_document().into_response();
+                }
+                let first_bytes_rx = first_bytes_rx.lock().unwrap().take().unwrap();
+                // The rest is only sent once the client has the first part,
+                // which can't happen if the gateway buffers the whole file
+                let body = async_stream::stream! {
+                    yield Ok::<_, std::io::Error>("%PDF-");
+                    let _ = tokio::time::timeout(Duration::from_secs(5), first_bytes_rx).await;
+                    yield Ok("1.7");
+                };
+                (
+                    [
+                        (axum::http::header::CONTENT_TYPE, "application/pdf"),
+                        (
+                            axum::http::header::CONTENT_DISPOSITION,
+                            "attachment; filename=\"manual.pdf\"",
+                        ),
+                    ],
+                    axum::body::Body::from_stream(body),
+                )
+                    .into_response()
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("user").await;
+        let download = |id: &str| {
+            app.client
+                .get(app.url(&format!("/api/v1/documents/{}/download", id)))
+                .bearer_auth(&token)
+                .send()
+        };
+
+        let mut resp = download(KNOWN_ID).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["content-type"], "application/pdf");
+        assert_eq!(
+            resp.headers()["content-disposition"],
+            "attachment; filename=\"manual.pdf\""
+        );
+        let first = tokio::time::timeout(Duration::from_secs(5), resp.chunk())
+            .await
+            .expect("first part relayed before the file is complete")
+            .unwrap()
+            .unwrap();
+        assert_eq!(&first[..], b"%PDF-");
+        first_bytes_tx.send(()).unwrap();
+        let mut received = first.to_vec();
+        while let Some(chunk) = resp.chunk().await.unwrap() {
+            received.extend_from_slice(&chunk);
+        }
+        assert_eq!(received, b"%PDF-1.7");
+
+        let resp = download(UNKNOWN_ID).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn proxy_error_page_is_reported_as_service_unavailable() {
+        let bad_gateway = || async {
+            (
+                axum::http::StatusCode::BAD_GATEWAY,
+                [(axum::http::header::CONTENT_TYPE, "text/html")],
+                "<html><body><h1>502 Bad Gateway</h1></body></html>",
+            )
+        };
+        let etl = Router::new()
+            .route("/api/v1/documents", get(bad_gateway))
+            .route("/api/v1/documents/{id}/file", get(bad_gateway));
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("user").await;
+
+        for path in [
+            "/api/v1/documents".to_string(),
+            format!("/api/v1/documents/{}/download", KNOWN_ID),
+        ] {
+            let response = app
+                .client
+                .get(app.url(&path))
+                .bearer_auth(&token)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(
+                response.status(),
+                StatusCode::SERVICE_UNAVAILABLE,
+                "{}",
+                path
+            );
+            let body: Value = response.json().await.unwrap();
+            assert_eq!(body["error"]["code"], "SERVICE_UNAVAILABLE", "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_idempotency_key_uploads_once_per_user() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let etl = Router::new().route(
+            "/api/v1/documents/upload",
+            post({
+                let calls = calls.clone();
+                move || async move {
+                    let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                    Json(json!({ "success": true, "data": { "call": call } }))
+                }
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+        let (_, other_token) = app.signed_in("editor").await;
+        let upload_with_key = |token: &str| {
+            let part = reqwest::multipart::Part::bytes(b"hello".to_vec())
+                .file_name("notes.txt")
+                .mime_str("text/plain")
+                .unwrap();
+            let request = app
+                .client
+                .post(app.url("/api/v1/documents/upload"))
+                .bearer_auth(token)
+                .header("Idempotency-Key", "upload-retry-1")
+                .multipart(reqwest::multipart::Form::new().part("file", part));
+            async move { request.send().await.unwrap().json::<Value>().await.unwrap() }
+        };
+
+        let first = upload_with_key(&token).await;
+        let retried = upload_with_key(&token).await;
+        assert_eq!(first["data"]["call"], 1);
+        assert_eq!(retried, first);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // The same key from another user is a different upload
+        let other = upload_with_key(&other_token).await;
+        assert_eq!(other["data"]["call"], 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
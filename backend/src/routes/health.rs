@@ -1,22 +1,365 @@
-use axum::{extract::State, response::Json};
+use axum::{extract::State, http::StatusCode, response::Json};
 use serde_json::{json, Value};
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
 
 use crate::AppState;
 
-pub async fn service_health(State(state): State<Arc<AppState>>) -> Json<Value> {
-    let db_ok = sqlx::query("SELECT 1")
-        .execute(&state.db)
-        .await
-        .is_ok();
+/// Upper bound for each individual dependency check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ServiceStatus {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+impl ServiceStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            ServiceStatus::Healthy => "healthy",
+            ServiceStatus::Degraded => "degraded",
+            ServiceStatus::Unhealthy => "unhealthy",
+        }
+    }
+}
+
+struct Check {
+    name: &'static str,
+    /// When a required dependency is unhealthy the endpoint returns 503
+    required: bool,
+    status: ServiceStatus,
+    latency_ms: u128,
+    /// Only reported by the LLM check
+    model_loaded: Option<bool>,
+}
+
+impl Check {
+    fn to_json(&self) -> Value {
+        let mut value = json!({
+            "status": self.status.as_str(),
+            "required": self.required,
+            "latency_ms": self.latency_ms,
+        });
+        if let Some(model_loaded) = self.model_loaded {
+            value["model_loaded"] = json!(model_loaded);
+        }
+        value
+    }
+}
+
+/// GET /health - Aggregate health of the gateway and its dependencies
+///
+/// All dependencies are checked concurrently, each bounded by `CHECK_TIMEOUT`.
+pub async fn service_health(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Value>) {
+    let etl_url = state.config.etl_service_url.join("/health");
+    let qdrant_url = state.config.qdrant_url.join("/healthz");
+
+    let (postgres, redis, etl, llm, qdrant) = tokio::join!(
+        timed("postgres", true, check_postgres(&state)),
+        timed("redis", true, check_redis(&state)),
+        timed("etl", true, check_http(&state, &etl_url)),
+        check_llm(&state),
+        timed("qdrant", false, check_http(&state, &qdrant_url)),
+    );
+    let checks = [postgres, redis, etl, llm, qdrant];
+
+    let required_down = checks
+        .iter()
+        .any(|c| c.required && c.status == ServiceStatus::Unhealthy);
+    let overall = if required_down {
+        ServiceStatus::Unhealthy
+    } else if checks.iter().all(|c| c.status == ServiceStatus::Healthy) {
+        ServiceStatus::Healthy
+    } else {
+        ServiceStatus::Degraded
+    };
+
+    let mut services = serde_json::Map::new();
+    services.insert("api_gateway".to_string(), json!({ "status": "healthy" }));
+    for check in &checks {
+        services.insert(check.name.to_string(), check.to_json());
+    }
+
+    let status_code = if required_down {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+
+    (
+        status_code,
+        Json(json!({
+            "success": !required_down,
+            "data": {
+                "status": overall.as_str(),
+                "services": services
+            }
+        })),
+    )
+}
 
+/// Last readiness result, reused for `readiness_cache_secs`
+#[derive(Default)]
+pub struct ReadinessCache {
+    last: Mutex<Option<(Instant, bool, Value)>>,
+}
+
+/// GET /health/live - Liveness probe; succeeds while the process is running
+pub async fn liveness() -> Json<Value> {
     Json(json!({
         "success": true,
-        "data": {
-            "services": {
-                "api_gateway": { "status": "healthy" },
-                "postgres": { "status": if db_ok { "healthy" } else { "unhealthy" } }
+        "data": { "status": "alive" }
+    }))
+}
+
+/// GET /health/ready - Readiness probe
+///
+/// Ready once a DB connection can be acquired, the ETL/LLM services are
+/// reachable and the LLM model is loaded. The result is cached so probe
+/// storms don't hammer upstreams; concurrent probes wait on the in-flight
+/// check instead of starting their own.
+pub async fn readiness(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Value>) {
+    let ttl = Duration::from_secs(state.config.readiness_cache_secs);
+    let mut last = state.readiness.last.lock().await;
+
+    let (ready, checks) = match last.as_ref() {
+        Some((checked_at, ready, checks)) if checked_at.elapsed() < ttl => (*ready, checks.clone()),
+        _ => {
+            let (ready, checks) = check_readiness(&state).await;
+            *last = Some((Instant::now(), ready, checks.clone()));
+            (ready, checks)
+        }
+    };
+    drop(last);
+
+    let status_code = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status_code,
+        Json(json!({
+            "success": ready,
+            "data": { "ready": ready, "checks": checks }
+        })),
+    )
+}
+
+async fn check_readiness(state: &AppState) -> (bool, Value) {
+    let etl_url = state.config.etl_service_url.join("/health");
+
+    let (postgres, etl, llm) = tokio::join!(
+        timed("postgres", true, async {
+            match state.db.acquire().await {
+                Ok(_) => ServiceStatus::Healthy,
+                Err(_) => ServiceStatus::Unhealthy,
             }
+        }),
+        timed("etl", true, check_http(state, &etl_url)),
+        check_llm(state),
+    );
+    let checks = [postgres, etl, llm];
+
+    let ready = checks
+        .iter()
+        .all(|c| c.status != ServiceStatus::Unhealthy && c.model_loaded != Some(false));
+    let checks_json = checks
+        .iter()
+        .map(|c| (c.name.to_string(), c.to_json()))
+        .collect::<serde_json::Map<_, _>>();
+
+    (ready, Value::Object(checks_json))
+}
+
+/// Run a check under `CHECK_TIMEOUT` and measure its latency.
+async fn timed<F>(name: &'static str, required: bool, check: F) -> Check
+where
+    F: Future<Output = ServiceStatus>,
+{
+    let start = Instant::now();
+    let status = tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or(ServiceStatus::Unhealthy);
+
+    Check {
+        name,
+        required,
+        status,
+        latency_ms: start.elapsed().as_millis(),
+        model_loaded: None,
+    }
+}
+
+/// LLM reachability plus whether its model has finished loading.
+///
+/// A reachable service whose model is not loaded yet is reported as degraded.
+async fn check_llm(state: &AppState) -> Check {
+    let health_url = state.config.llm_service_url.join("/health");
+    let status_url = state.config.llm_service_url.join("/status");
+
+    let (mut check, model_loaded) = tokio::join!(
+        timed("llm", true, check_http(state, &health_url)),
+        tokio::time::timeout(CHECK_TIMEOUT, check_model_loaded(state, &status_url)),
+    );
+    let model_loaded = model_loaded.unwrap_or(false);
+
+    if !model_loaded && check.status == ServiceStatus::Healthy {
+        check.status = ServiceStatus::Degraded;
+    }
+    check.model_loaded = Some(model_loaded);
+    check
+}
+
+async fn check_postgres(state: &AppState) -> ServiceStatus {
+    match sqlx::query("SELECT 1").execute(&state.db).await {
+        Ok(_) => ServiceStatus::Healthy,
+        Err(_) => ServiceStatus::Unhealthy,
+    }
+}
+
+async fn check_redis(state: &AppState) -> ServiceStatus {
+    let mut conn = state.redis.clone();
+    match redis::cmd("PING").query_async::<String>(&mut conn).await {
+        Ok(_) => ServiceStatus::Healthy,
+        Err(_) => ServiceStatus::Unhealthy,
+    }
+}
+
+/// GET an upstream health URL; a 2xx reporting `"status": "degraded"` counts as degraded.
+async fn check_http(state: &AppState, url: &str) -> ServiceStatus {
+    let resp = match state.http.get(url).timeout(CHECK_TIMEOUT).send().await {
+        Ok(resp) => resp,
+        Err(_) => return ServiceStatus::Unhealthy,
+    };
+
+    if !resp.status().is_success() {
+        return ServiceStatus::Unhealthy;
+    }
+
+    match resp.json::<Value>().await {
+        Ok(body) if body.get("status").and_then(|s| s.as_str()) == Some("degraded") => {
+            ServiceStatus::Degraded
         }
-    }))
+        _ => ServiceStatus::Healthy,
+    }
+}
+
+/// Read `model_loaded` from the LLM `/status` endpoint; any failure counts as not loaded.
+async fn check_model_loaded(state: &AppState, url: &str) -> bool {
+    let resp = match state.http.get(url).timeout(CHECK_TIMEOUT).send().await {
+        Ok(resp) if resp.status().is_success() => resp,
+        _ => return false,
+    };
+
+    resp.json::<Value>()
+        .await
+        .ok()
+        .and_then(|body| body.get("model_loaded").and_then(|v| v.as_bool()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Json, Router};
+    use reqwest::StatusCode;
+    use serde_json::{json, Value};
+
+    use crate::test_support::{self, TestApp};
+
+    /// Stand-in answering the ETL, LLM and Qdrant health endpoints
+    fn healthy_upstream() -> Router {
+        Router::new()
+            .route("/health", get(|| async { Json(json!({ "status": "ok" })) }))
+            .route("/healthz", get(|| async { "healthz check passed" }))
+            .route(
+                "/status",
+                get(|| async { Json(json!({ "model_loaded": true })) }),
+            )
+    }
+
+    async fn health(app: &TestApp) -> (StatusCode, Value) {
+        let resp = app
+            .client
+            .get(app.url("/api/v1/health"))
+            .send()
+            .await
+            .unwrap();
+        (resp.status(), resp.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn reachable_dependencies_are_healthy() {
+        let upstream = test_support::mock_service(healthy_upstream()).await;
+        let mut config = test_support::config();
+        config.etl_service_url = upstream.clone();
+        config.llm_service_url = upstream.clone();
+        config.qdrant_url = upstream;
+        let app = test_support::spawn(config).await;
+
+        let (status, body) = health(&app).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["status"], "healthy");
+        let services = &body["data"]["services"];
+        for name in ["postgres", "redis", "etl", "llm", "qdrant"] {
+            assert_eq!(services[name]["status"], "healthy", "{}", name);
+            assert!(services[name]["latency_ms"].is_u64(), "{}", name);
+        }
+        assert_eq!(services["llm"]["model_loaded"], true);
+    }
+
+    #[tokio::test]
+    async fn unreachable_required_dependency_is_unavailable() {
+        let upstream = test_support::mock_service(healthy_upstream()).await;
+        let mut config = test_support::config();
+        config.llm_service_url = upstream.clone();
+        config.qdrant_url = upstream;
+        let app = test_support::spawn(config).await;
+
+        let (status, body) = health(&app).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["success"], false);
+        assert_eq!(body["data"]["status"], "unhealthy");
+        assert_eq!(body["data"]["services"]["etl"]["status"], "unhealthy");
+        assert_eq!(body["data"]["services"]["llm"]["status"], "healthy");
+    }
+
+    #[tokio::test]
+    async fn llm_still_loading_its_model_is_degraded_and_not_ready() {
+        let upstream = test_support::mock_service(healthy_upstream()).await;
+        let loading = Router::new()
+            .route("/health", get(|| async { Json(json!({ "status": "ok" })) }))
+            .route(
+                "/status",
+                get(|| async { Json(json!({ "model_loaded": false })) }),
+            );
+        let mut config = test_support::config();
+        config.etl_service_url = upstream.clone();
+        config.qdrant_url = upstream;
+        config.llm_service_url = test_support::mock_service(loading).await;
+        let app = test_support::spawn(config).await;
+
+        let (status, body) = health(&app).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["status"], "degraded");
+        let llm = &body["data"]["services"]["llm"];
+        assert_eq!(llm["status"], "degraded");
+        assert_eq!(llm["model_loaded"], false);
+
+        let ready = app
+            .client
+            .get(app.url("/api/v1/health/ready"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(ready.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = ready.json().await.unwrap();
+        assert_eq!(body["data"]["ready"], false);
+        assert_eq!(body["data"]["checks"]["llm"]["model_loaded"], false);
+    }
 }
@@ -1,24 +1,142 @@
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
-    routing::{get, post},
+    routing::{delete, get, patch, post},
     Router,
 };
 use std::sync::Arc;
 
-use crate::auth::middleware::auth_middleware;
+use crate::auth::middleware::{
+    auth_middleware, require_password_changed, require_role, require_scope,
+};
+use crate::auth::scopes;
+use crate::metrics;
+use crate::rate_limit::rate_limit;
 use crate::AppState;
 
+pub mod admin;
 pub mod auth;
 pub mod chat;
 pub mod documents;
 pub mod health;
+pub mod version;
+
+/// Roles allowed to modify the document corpus
+const DOCUMENT_WRITERS: &[&str] = &["admin", "editor"];
+
+/// Roles allowed to manage user accounts
+const ADMINS: &[&str] = &["admin"];
+
+/// Page size for list endpoints when `limit` is not given, and its upper bound
+const DEFAULT_PAGE_LIMIT: i64 = 20;
+const MAX_PAGE_LIMIT: i64 = 100;
+
+/// Headroom above `max_upload_bytes` for multipart boundaries and part headers
+const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
 
 pub fn api_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
-    // Protected routes requiring authentication
-    let protected = Router::new()
+    // Routes restricted to document writers
+    let document_writers = Router::new()
+        .route(
+            "/documents/upload",
+            post(documents::upload_document).layer(DefaultBodyLimit::max(
+                state.config.max_upload_bytes + MULTIPART_OVERHEAD_BYTES,
+            )),
+        )
+        .route(
+            "/documents/upload/batch",
+            post(documents::upload_documents).layer(DefaultBodyLimit::max(
+                state.config.max_upload_bytes * documents::MAX_BATCH_UPLOAD_FILES
+                    + MULTIPART_OVERHEAD_BYTES,
+            )),
+        )
+        .route("/documents/{id}", delete(documents::delete_document))
+        .route("/documents/{id}/reprocess", post(documents::reprocess_document))
+        .route_layer(middleware::from_fn(|req, next| {
+            require_role(DOCUMENT_WRITERS, req, next)
+        }))
+        .route_layer(middleware::from_fn(|req, next| {
+            require_scope(scopes::DOCUMENTS_WRITE, req, next)
+        }));
+
+    // Account management, admin only
+    let admin = Router::new()
+        .route("/admin/users", get(admin::list_users))
+        .route("/admin/users/{id}", patch(admin::update_user))
+        .route("/admin/users/{id}/reset-password", post(admin::reset_password))
+        .route(
+            "/admin/api-keys",
+            get(admin::list_api_keys).post(admin::create_api_key),
+        )
+        .route("/admin/api-keys/{id}", delete(admin::revoke_api_key))
+        .route_layer(middleware::from_fn(|req, next| {
+            require_role(ADMINS, req, next)
+        }))
+        .route_layer(middleware::from_fn(|req, next| {
+            require_scope(scopes::ADMIN_USERS, req, next)
+        }));
+
+    let moderation = Router::new()
+        .route("/admin/moderation/reload", post(admin::reload_blocklist))
+        .route_layer(middleware::from_fn(|req, next| {
+            require_role(ADMINS, req, next)
+        }))
+        .route_layer(middleware::from_fn(|req, next| {
+            require_scope(scopes::ADMIN_MODERATION, req, next)
+        }));
+
+    let stats = Router::new()
+        .route("/admin/stats", get(admin::stats))
+        .route_layer(middleware::from_fn(|req, next| {
+            require_role(ADMINS, req, next)
+        }))
+        .route_layer(middleware::from_fn(|req, next| {
+            require_scope(scopes::ADMIN_STATS, req, next)
+        }));
+
+    // Rate-limited per user
+    let rate_limited = Router::new()
         .route("/chat/stream", post(chat::chat_stream))
-        .route("/documents/upload", post(documents::upload_document))
+        .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit));
+
+    let chat = Router::new()
+        .route("/chat/conversations", get(chat::list_conversations))
+        .route("/chat/conversations/{id}", get(chat::get_conversation))
+        .route("/chat/ws", get(chat::chat_ws))
+        .route("/chat/{stream_id}/cancel", post(chat::cancel_stream))
+        .route("/search", post(chat::search))
+        .merge(rate_limited)
+        .route_layer(middleware::from_fn(|req, next| {
+            require_scope(scopes::CHAT_READ, req, next)
+        }));
+
+    let document_readers = Router::new()
         .route("/documents", get(documents::list_documents))
+        .route("/documents/status", post(documents::batch_document_status))
+        .route("/documents/{id}", get(documents::get_document))
+        .route("/documents/{id}/status", get(documents::get_document_status))
+        .route("/documents/{id}/download", get(documents::download_document))
+        .route_layer(middleware::from_fn(|req, next| {
+            require_scope(scopes::DOCUMENTS_READ, req, next)
+        }));
+
+    // Closed to accounts with an unchanged temporary password
+    let password_changed = Router::new()
+        .merge(chat)
+        .merge(document_readers)
+        .merge(document_writers)
+        .merge(admin)
+        .merge(moderation)
+        .merge(stats)
+        .route_layer(middleware::from_fn(require_password_changed));
+
+    // Protected routes requiring authentication; the caller's own account needs no scope
+    let protected = Router::new()
+        .route("/auth/logout", post(auth::logout))
+        .route("/auth/logout-all", post(auth::logout_all))
+        .route("/auth/me", get(auth::me).delete(auth::delete_me))
+        .route("/auth/me/password", post(auth::change_password))
+        .merge(password_changed)
         .layer(middleware::from_fn_with_state(
             state,
             auth_middleware,
@@ -27,9 +145,142 @@ pub fn api_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
     // Public routes (no auth required)
     let public = Router::new()
         .route("/auth/login", post(auth::login))
+        .route("/auth/register", post(auth::register))
         .route("/auth/refresh", post(auth::refresh))
-        .route("/auth/logout", post(auth::logout))
-        .route("/health", get(health::service_health));
+        .route("/health", get(health::service_health))
+        .route("/health/live", get(health::liveness))
+        .route("/health/ready", get(health::readiness))
+        .route("/metrics", get(metrics::metrics_handler))
+        .route("/version", get(version::version));
 
     public.merge(protected)
 }
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use crate::auth::{api_keys, scopes};
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn only_document_writers_can_delete_documents() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (_, token) = app.signed_in("user").await;
+
+        let response = app
+            .client
+            .delete(app.url("/api/v1/documents/7f6f6d5c-7a35-4d8e-9a7b-0d6c3f0e2a11"))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn key_without_documents_write_can_chat_but_not_upload() {
+        let app = test_support::spawn(test_support::config()).await;
+        // An editor may upload, but this key only lets the owner chat
+        let owner = app.user("editor").await;
+        let key = api_keys::generate();
+        sqlx::query(
+            "INSERT INTO api_keys (user_id, name, key_prefix, key_hash, scopes) \
+             VALUES ($1, 'chat only', $2, $3, $4)",
+        )
+        .bind(owner.id)
+        .bind(&key.prefix)
+        .bind(&key.hash)
+        .bind(vec![scopes::CHAT_READ.to_string()])
+        .execute(&app.state.db)
+        .await
+        .unwrap();
+
+        let response = app
+            .client
+            .post(app.url("/api/v1/documents/upload"))
+            .header("x-api-key", &key.plaintext)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "FORBIDDEN");
+
+        let response = app
+            .client
+            .get(app.url("/api/v1/chat/conversations"))
+            .header("x-api-key", &key.plaintext)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn temporary_password_limits_the_account_to_changing_it() {
+        let app = test_support::spawn(test_support::config()).await;
+        let (user, token) = app.signed_in("user").await;
+        let key = api_keys::generate();
+        sqlx::query(
+            "INSERT INTO api_keys (user_id, name, key_prefix, key_hash, scopes) \
+             VALUES ($1, 'chat', $2, $3, $4)",
+        )
+        .bind(user.id)
+        .bind(&key.prefix)
+        .bind(&key.hash)
+        .bind(vec![scopes::CHAT_READ.to_string()])
+        .execute(&app.state.db)
+        .await
+        .unwrap();
+        // As an admin password reset leaves it
+        sqlx::query("UPDATE users SET must_change_password = true WHERE id = $1")
+            .bind(user.id)
+            .execute(&app.state.db)
+            .await
+            .unwrap();
+
+        let url = app.url("/api/v1/chat/conversations");
+        let by_token = app.client.get(&url).bearer_auth(&token);
+        let by_key = app.client.get(&url).header("x-api-key", &key.plaintext);
+        for request in [by_token, by_key] {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            let body: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(body["error"]["code"], "PASSWORD_CHANGE_REQUIRED");
+        }
+
+        let me = app
+            .client
+            .get(app.url("/api/v1/auth/me"))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(me.status(), StatusCode::OK);
+        let body: serde_json::Value = me.json().await.unwrap();
+        assert_eq!(body["data"]["must_change_password"], true);
+
+        let changed = app
+            .client
+            .post(app.url("/api/v1/auth/me/password"))
+            .bearer_auth(&token)
+            .json(&serde_json::json!({
+                "current_password": test_support::PASSWORD,
+                "new_password": "Changed-password-2",
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(changed.status(), StatusCode::OK);
+
+        let response = app
+            .client
+            .get(&url)
+            .header("x-api-key", &key.plaintext)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
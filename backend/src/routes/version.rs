@@ -0,0 +1,52 @@
+use axum::{extract::State, response::Json};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::AppState;
+
+/// GET /version - Build metadata of the running gateway
+///
+/// `git_sha` and `build_time` are embedded by `build.rs`.
+pub async fn version(State(state): State<Arc<AppState>>) -> Json<Value> {
+    Json(json!({
+        "success": true,
+        "data": {
+            "service": "api-gateway",
+            "version": env!("CARGO_PKG_VERSION"),
+            "git_sha": env!("GIT_SHA"),
+            "build_time": env!("BUILD_TIME"),
+            "app_env": state.config.app_env,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use serde_json::Value;
+
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn version_reports_the_build() {
+        let app = test_support::spawn(test_support::config()).await;
+
+        let response = app
+            .client
+            .get(app.url("/api/v1/version"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+
+        let data = &body["data"];
+        for field in ["service", "version", "git_sha", "build_time", "app_env"] {
+            let value = data[field].as_str().unwrap_or_default();
+            assert!(!value.is_empty(), "{} is empty", field);
+        }
+        assert_eq!(data["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(data["app_env"], app.state.config.app_env);
+        assert!(chrono::DateTime::parse_from_rfc3339(data["build_time"].as_str().unwrap()).is_ok());
+    }
+}
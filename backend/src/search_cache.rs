@@ -0,0 +1,44 @@
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+const KEY_PREFIX: &str = "search_cache:";
+
+/// Cache key for an ETL search.
+///
+/// The query is normalized (case and whitespace) so trivially different
+/// phrasings share an entry, and hashed to keep keys short. `scope` separates
+/// entries that must not be shared, e.g. per user.
+pub fn key(scope: &str, query: &str, search_mode: &str, top_k: u32, min_score: f64) -> String {
+    let normalized = query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+
+    let mut hasher = Sha256::new();
+    hasher.update(format!(
+        "{}\n{}\n{}\n{}\n{}",
+        scope, search_mode, top_k, min_score, normalized
+    ));
+    format!("{}{:x}", KEY_PREFIX, hasher.finalize())
+}
+
+/// Cached ETL search response; unreadable entries count as a miss.
+pub async fn get(redis: &ConnectionManager, key: &str) -> Result<Option<Value>, redis::RedisError> {
+    let mut conn = redis.clone();
+    let cached: Option<String> = conn.get(key).await?;
+    Ok(cached.and_then(|raw| serde_json::from_str(&raw).ok()))
+}
+
+pub async fn put(
+    redis: &ConnectionManager,
+    key: &str,
+    body: &Value,
+    ttl_secs: u64,
+) -> Result<(), redis::RedisError> {
+    let mut conn = redis.clone();
+    conn.set_ex::<_, _, ()>(key, body.to_string(), ttl_secs)
+        .await
+}
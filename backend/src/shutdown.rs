@@ -0,0 +1,203 @@
+use axum::serve::Listener;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Resolves on SIGINT (Ctrl+C) or SIGTERM.
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// `TcpListener` that keeps count of the connections currently open, so
+/// shutdown can report what is still draining.
+pub struct TrackedListener {
+    inner: TcpListener,
+    open: Arc<AtomicUsize>,
+}
+
+impl TrackedListener {
+    pub fn new(inner: TcpListener) -> Self {
+        Self {
+            inner,
+            open: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Handle for reading the open connection count after the listener is
+    /// handed to `axum::serve`.
+    pub fn open_connections(&self) -> OpenConnections {
+        OpenConnections(self.open.clone())
+    }
+}
+
+impl Listener for TrackedListener {
+    type Io = TrackedStream;
+    type Addr = std::net::SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        let (stream, addr) = Listener::accept(&mut self.inner).await;
+        self.open.fetch_add(1, Ordering::Relaxed);
+        let stream = TrackedStream {
+            inner: stream,
+            open: self.open.clone(),
+        };
+        (stream, addr)
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        self.inner.local_addr()
+    }
+}
+
+#[derive(Clone)]
+pub struct OpenConnections(Arc<AtomicUsize>);
+
+impl OpenConnections {
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Accepted connection; decrements the open count when the server drops it.
+pub struct TrackedStream {
+    inner: TcpStream,
+    open: Arc<AtomicUsize>,
+}
+
+impl Drop for TrackedStream {
+    fn drop(&mut self) {
+        self.open.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl AsyncRead for TrackedStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TrackedStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, serve::Listener, Router};
+    use std::future::IntoFuture;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::{oneshot, Notify};
+
+    use super::TrackedListener;
+
+    #[tokio::test]
+    async fn shutdown_stops_accepting_but_finishes_in_flight_requests() {
+        let started = Arc::new(Notify::new());
+        let release = Arc::new(Notify::new());
+        let app = Router::new().route(
+            "/slow",
+            get({
+                let (started, release) = (started.clone(), release.clone());
+                move || async move {
+                    started.notify_one();
+                    release.notified().await;
+                    "done"
+                }
+            }),
+        );
+
+        let listener = TrackedListener::new(TcpListener::bind("127.0.0.1:0").await.unwrap());
+        let addr = Listener::local_addr(&listener).unwrap();
+        let open = listener.open_connections();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    stopped.await.ok();
+                })
+                .into_future(),
+        );
+
+        let in_flight = tokio::spawn(reqwest::get(format!("http://{}/slow", addr)));
+        started.notified().await;
+        assert_eq!(open.get(), 1);
+        stop.send(()).unwrap();
+
+        // The listener closes while the request is still being handled
+        let mut refused = false;
+        for _ in 0..50 {
+            if TcpStream::connect(addr).await.is_err() {
+                refused = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(refused, "new connections are still accepted");
+        assert!(!server.is_finished());
+
+        release.notify_one();
+        let response = in_flight.await.unwrap().unwrap();
+        assert_eq!(response.text().await.unwrap(), "done");
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server stops once drained")
+            .unwrap()
+            .unwrap();
+        assert_eq!(open.get(), 0);
+    }
+}
@@ -0,0 +1,183 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use futures_util::StreamExt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::AppState;
+
+/// Middleware logging a warning for requests slower than `slow_request_ms`.
+///
+/// Ordinary responses are timed until the handler returns them. An SSE
+/// response stays open for the whole answer, so it is timed until its first
+/// chunk is sent instead.
+pub async fn log_slow_requests(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if state.config.slow_request_ms == 0 {
+        return next.run(req).await;
+    }
+
+    let threshold = Duration::from_millis(state.config.slow_request_ms);
+    let slow = SlowRequest {
+        method: req.method().clone(),
+        path: req.uri().path().to_string(),
+        // The body may be polled outside the request's task-local scope
+        request_id: crate::request_id::current().unwrap_or_default(),
+        start: Instant::now(),
+    };
+
+    let response = next.run(req).await;
+    let status = response.status();
+
+    if !is_event_stream(&response) {
+        slow.check(threshold, status);
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let mut pending = Some(slow);
+    let body = body.into_data_stream().inspect(move |_| {
+        if let Some(slow) = pending.take() {
+            slow.check(threshold, status);
+        }
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+struct SlowRequest {
+    method: Method,
+    path: String,
+    request_id: String,
+    start: Instant,
+}
+
+impl SlowRequest {
+    fn check(&self, threshold: Duration, status: StatusCode) {
+        let elapsed = self.start.elapsed();
+        if elapsed < threshold {
+            return;
+        }
+        tracing::warn!(
+            method = %self.method,
+            path = %self.path,
+            status = status.as_u16(),
+            duration_ms = elapsed.as_millis() as u64,
+            threshold_ms = threshold.as_millis() as u64,
+            request_id = %self.request_id,
+            "Slow request"
+        );
+    }
+}
+
+fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Json, Router};
+    use serde_json::{json, Value};
+    use std::io;
+    use std::sync::Mutex;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use super::*;
+    use crate::test_support;
+
+    /// Collects formatted log lines so a test can inspect them
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Captured {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self {
+            self.clone()
+        }
+    }
+
+    impl Captured {
+        fn slow_requests(&self) -> Vec<Value> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap())
+                .filter(|line| line["fields"]["message"] == "Slow request")
+                .collect()
+        }
+    }
+
+    async fn list_documents(slow_request_ms: u64) -> Vec<Value> {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_writer(captured.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let etl = Router::new().route(
+            "/api/v1/documents",
+            get(|| async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Json(json!({ "success": true, "data": [] }))
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        config.slow_request_ms = slow_request_ms;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+
+        let response = app
+            .client
+            .get(app.url("/api/v1/documents"))
+            .bearer_auth(&token)
+            .header("x-request-id", "slow-1")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        captured.slow_requests()
+    }
+
+    #[tokio::test]
+    async fn slow_request_is_logged_with_its_details() {
+        let logged = list_documents(100).await;
+        assert_eq!(logged.len(), 1);
+        let fields = &logged[0]["fields"];
+        assert_eq!(logged[0]["level"], "WARN");
+        assert_eq!(fields["method"], "GET");
+        assert_eq!(fields["path"], "/api/v1/documents");
+        assert_eq!(fields["status"], 200);
+        assert_eq!(fields["request_id"], "slow-1");
+        assert!(fields["duration_ms"].as_u64().unwrap() >= 200);
+    }
+
+    #[tokio::test]
+    async fn request_under_the_threshold_is_not_logged() {
+        assert!(list_documents(10_000).await.is_empty());
+    }
+}
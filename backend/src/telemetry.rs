@@ -0,0 +1,121 @@
+use axum::http::HeaderMap;
+use opentelemetry::{global, trace::TraceError, KeyValue};
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator, runtime, trace::TracerProvider, Resource,
+};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Enables OTLP export when set; the exporter reads it (and the other
+/// standard `OTEL_*` variables) itself.
+const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const SERVICE_NAME: &str = "api-gateway";
+
+/// Build an OTLP/HTTP tracer provider, or `None` when export is not configured.
+///
+/// Also installs the W3C trace-context propagator, so `traceparent` headers
+/// are only read and written while export is enabled.
+pub fn init_tracer() -> Result<Option<TracerProvider>, TraceError> {
+    if std::env::var(OTLP_ENDPOINT_ENV).map_or(true, |v| v.is_empty()) {
+        return Ok(None);
+    }
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()?;
+
+    // OTEL_SERVICE_NAME, when set, wins over the default name
+    let mut resource = Resource::default();
+    if std::env::var("OTEL_SERVICE_NAME").is_err() {
+        resource = resource.merge(&Resource::new([KeyValue::new(
+            "service.name",
+            SERVICE_NAME,
+        )]));
+    }
+
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(resource)
+        .build();
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(Some(provider))
+}
+
+/// Continue a trace started by the caller, if its headers carry one.
+pub fn set_parent_from_headers(span: &Span, headers: &HeaderMap) {
+    let parent = global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(headers)));
+    span.set_parent(parent);
+}
+
+/// Trace-context headers for an outbound request made within the current span.
+///
+/// Empty when export is disabled.
+pub fn trace_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let context = Span::current().context();
+    global::get_text_map_propagator(|p| {
+        p.inject_context(&context, &mut HeaderInjector(&mut headers))
+    });
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Json, Router};
+    use opentelemetry::trace::TracerProvider as _;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::test_support;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+    #[tokio::test]
+    async fn outbound_requests_carry_the_callers_trace() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let received = Arc::new(Mutex::new(None));
+        let etl = Router::new().route(
+            "/api/v1/documents",
+            get({
+                let received = received.clone();
+                move |headers: HeaderMap| async move {
+                    *received.lock().unwrap() = headers
+                        .get("traceparent")
+                        .map(|v| v.to_str().unwrap().to_string());
+                    Json(json!({ "success": true, "data": [] }))
+                }
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+
+        let incoming = format!("00-{}-00f067aa0ba902b7-01", TRACE_ID);
+        let response = app
+            .client
+            .get(app.url("/api/v1/documents"))
+            .bearer_auth(&token)
+            .header("traceparent", &incoming)
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        let outbound = received.lock().unwrap().clone().expect("traceparent sent");
+        let parts: Vec<&str> = outbound.split('-').collect();
+        assert_eq!(parts.len(), 4, "{}", outbound);
+        assert_eq!(parts[1], TRACE_ID);
+        // The gateway's own span is the parent of the upstream call
+        assert_ne!(parts[2], "00f067aa0ba902b7");
+    }
+}
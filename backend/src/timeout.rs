@@ -0,0 +1,78 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::AppError;
+use crate::AppState;
+
+/// Chat answers are streamed for as long as the LLM keeps generating; their
+/// upstream calls carry their own timeouts
+const STREAMING_PATHS: &[&str] = &["/api/v1/chat/stream", "/api/v1/chat/ws"];
+
+/// Fail requests with 504 when no response is ready within `request_timeout_secs`.
+///
+/// The handler is dropped on expiry, which also cancels its upstream calls.
+/// Only producing the response is bounded, not sending its body.
+pub async fn request_timeout(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let timeout_secs = state.config.request_timeout_secs;
+    if timeout_secs == 0 || STREAMING_PATHS.contains(&req.uri().path()) {
+        return Ok(next.run(req).await);
+    }
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    match tokio::time::timeout(Duration::from_secs(timeout_secs), next.run(req)).await {
+        Ok(response) => Ok(response),
+        Err(_) => {
+            tracing::warn!(%method, %path, timeout_secs, "Request timed out");
+            Err(AppError::GatewayTimeout)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Json, Router};
+    use reqwest::StatusCode;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn slow_handler_times_out_with_504() {
+        let etl = Router::new().route(
+            "/api/v1/documents",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Json(json!({ "success": true, "data": [] }))
+            }),
+        );
+        let mut config = test_support::config();
+        config.etl_service_url = test_support::mock_service(etl).await;
+        config.etl_timeout_ms = 10_000;
+        config.request_timeout_secs = 1;
+        let app = test_support::spawn(config).await;
+        let (_, token) = app.signed_in("editor").await;
+
+        let response = app
+            .client
+            .get(app.url("/api/v1/documents"))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"]["code"], "GATEWAY_TIMEOUT");
+    }
+}
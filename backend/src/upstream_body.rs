@@ -0,0 +1,34 @@
+use reqwest::{header, Response};
+
+use crate::redact;
+
+/// Longest part of an unexpected upstream body written to the log
+const LOG_EXCERPT_CHARS: usize = 500;
+
+/// Whether an upstream response declares a JSON body
+pub fn is_json(resp: &Response) -> bool {
+    let media_type = content_type(resp);
+    let media_type = media_type.split(';').next().unwrap_or_default().trim();
+    media_type.eq_ignore_ascii_case("application/json") || media_type.ends_with("+json")
+}
+
+/// The response's `Content-Type`, empty when missing or not text
+pub fn content_type(resp: &Response) -> String {
+    resp.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Start of the response body with credentials masked, for logging a
+/// response that can't be used.
+pub async fn excerpt(resp: Response) -> String {
+    match resp.text().await {
+        Ok(body) => redact::body(&body)
+            .chars()
+            .take(LOG_EXCERPT_CHARS)
+            .collect(),
+        Err(e) => format!("<unreadable body: {}>", e),
+    }
+}
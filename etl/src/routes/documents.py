@@ -1,17 +1,20 @@
 """Document management routes."""
 import logging
+from urllib.parse import quote
 from uuid import UUID
 
-from fastapi import APIRouter, UploadFile, File, Form, HTTPException
-from fastapi.responses import JSONResponse
+from fastapi import APIRouter, UploadFile, File, Form, HTTPException, Query
+from fastapi.responses import JSONResponse, StreamingResponse
+from minio.error import S3Error
 
-from src.services import pipeline, qdrant_client, minio_client
+from src.services import document_store, pipeline, qdrant_client, minio_client
 
 logger = logging.getLogger(__name__)
 router = APIRouter()
 
-ALLOWED_EXTENSIONS = {".pdf", ".docx"}
+ALLOWED_EXTENSIONS = {".pdf", ".docx", ".txt", ".md"}
 MAX_FILE_SIZE = 100 * 1024 * 1024  # 100MB
+MAX_PAGE_LIMIT = 100
 
 
 @router.post("/documents/upload")
@@ -19,8 +22,10 @@ async def upload_document(
     file: UploadFile = File(...),
     document_type: str = Form(""),
     department: str = Form(""),
+    user_id: UUID | None = Form(None),
+    username: str = Form(""),
 ) -> JSONResponse:
-    """Upload and process a document."""
+    """Upload and process a document, owned by `user_id` when the gateway sends one."""
     file_name = file.filename or "unknown"
     file_ext = "." + file_name.rsplit(".", 1)[-1].lower()
 
@@ -37,11 +42,16 @@ async def upload_document(
 
     try:
         result = await pipeline.process_document(
-            file_bytes, file_name, document_type, department
+            file_bytes,
+            file_name,
+            document_type,
+            department,
+            uploaded_by=str(user_id) if user_id else None,
         )
     except Exception as e:
         logger.exception("ETL pipeline failed for %s", file_name)
         raise HTTPException(status_code=500, detail=f"Processing failed: {e}")
+    logger.info("Processed %s uploaded by %s", file_name, username or "unknown user")
 
     return JSONResponse(
         status_code=202,
@@ -58,17 +68,168 @@ async def upload_document(
 
 
 @router.get("/documents")
-async def list_documents() -> JSONResponse:
-    """List all documents (placeholder)."""
+async def list_documents(
+    user_id: UUID | None = None,
+    limit: int = Query(20, ge=1, le=MAX_PAGE_LIMIT),
+    offset: int = Query(0, ge=0),
+    sort: str = "-created_at",
+) -> JSONResponse:
+    """List a page of documents, only those uploaded by `user_id` when given.
+
+    `sort` is `created_at` or `file_name`, prefixed with `-` for descending order.
+    """
+    if sort.removeprefix("-") not in document_store.SORTABLE_COLUMNS:
+        raise HTTPException(
+            status_code=400,
+            detail=f"Unsupported sort: {sort}. Allowed: {document_store.SORTABLE_COLUMNS}",
+        )
+
+    documents, total = await document_store.list_documents(
+        str(user_id) if user_id else None, limit=limit, offset=offset, sort=sort
+    )
+    return JSONResponse(
+        content={
+            "success": True,
+            "data": documents,
+            "meta": {"total": total, "limit": limit, "offset": offset},
+        }
+    )
+
+
+@router.get("/documents/{document_id}")
+async def get_document(document_id: UUID, user_id: UUID | None = None) -> JSONResponse:
+    """Metadata and processing status of one document."""
+    document = await _get_document(document_id, user_id)
+    return JSONResponse(content={"success": True, "data": document})
+
+
+@router.get("/documents/{document_id}/status")
+async def get_document_status(document_id: UUID, user_id: UUID | None = None) -> JSONResponse:
+    """Ingestion status of one document, for the gateway to poll."""
+    document = await _get_document(document_id, user_id)
+
+    status = document["etl_status"]
+    data = {
+        "document_id": str(document_id),
+        "status": status,
+        "chunk_count": document.get("chunk_count"),
+    }
+    if status == "completed":
+        data["progress"] = 100
+    if status == "failed":
+        data["error"] = document.get("etl_error")
+    return JSONResponse(content={"success": True, "data": data})
+
+
+@router.get("/documents/{document_id}/file")
+async def download_document_file(
+    document_id: UUID, user_id: UUID | None = None
+) -> StreamingResponse:
+    """Stream the originally uploaded file of one document."""
+    document = await _get_document(document_id, user_id)
+
+    try:
+        chunks = minio_client.stream_file(document["minio_object_key"])
+    except S3Error as e:
+        if e.code != "NoSuchKey":
+            raise
+        raise HTTPException(status_code=404, detail="Document file not found")
+
+    file_name = document["file_name"]
+    # Plain ASCII for old clients, the exact name as RFC 5987 `filename*`
+    fallback = "".join(
+        c if c.isascii() and c.isprintable() and c not in '"\\' else "_" for c in file_name
+    )
+    headers = {
+        "Content-Disposition": (
+            f"attachment; filename=\"{fallback}\"; filename*=UTF-8''{quote(file_name)}"
+        ),
+    }
+    if document.get("file_size") is not None:
+        headers["Content-Length"] = str(document["file_size"])
+    return StreamingResponse(
+        chunks,
+        media_type=pipeline.CONTENT_TYPES.get(
+            document.get("file_type") or "", "application/octet-stream"
+        ),
+        headers=headers,
+    )
+
+
+@router.post("/documents/{document_id}/reprocess")
+async def reprocess_document(document_id: UUID, user_id: UUID | None = None) -> JSONResponse:
+    """Re-ingest a stored document, e.g. after a pipeline change."""
+    document = await _get_document(document_id, user_id)
+    if document["etl_status"] == "processing":
+        raise HTTPException(status_code=409, detail="Document is already being processed")
+
+    try:
+        result = await pipeline.reprocess_document(document)
+    except Exception as e:
+        logger.exception("Reprocessing failed for %s", document_id)
+        raise HTTPException(status_code=500, detail=f"Processing failed: {e}")
+    logger.info("Reprocessed document %s", document_id)
+
+    return JSONResponse(content={"success": True, "data": result})
+
+
+@router.delete("/documents/{document_id}")
+async def delete_document(document_id: UUID, user_id: UUID | None = None) -> JSONResponse:
+    """Delete a document with its chunks and stored file."""
+    document = await _get_document(document_id, user_id)
+
+    await _delete(document)
+    return JSONResponse(
+        content={
+            "success": True,
+            "data": {"document_id": str(document_id), "deleted": True},
+        }
+    )
+
+
+@router.delete("/users/{user_id}/documents")
+async def delete_user_documents(user_id: UUID) -> JSONResponse:
+    """Delete every document uploaded by `user_id`, for account deletion."""
+    deleted = 0
+    while True:
+        # Each batch is deleted before the next is read, so offset stays 0
+        documents, _ = await document_store.list_documents(
+            str(user_id), limit=MAX_PAGE_LIMIT
+        )
+        if not documents:
+            break
+        for document in documents:
+            await _delete(document)
+        deleted += len(documents)
+    logger.info("Deleted %d documents of user %s", deleted, user_id)
+
     return JSONResponse(
         content={
             "success": True,
-            "data": [],
-            "meta": {"total": 0, "page": 1, "limit": 20},
+            "data": {"user_id": str(user_id), "deleted_documents": deleted},
         }
     )
 
 
+async def _get_document(document_id: UUID, user_id: UUID | None) -> dict:
+    """The document, or a 404 when it doesn't exist or, given `user_id`, wasn't uploaded by them.
+
+    Someone else's document is reported as missing, so its id can't be probed.
+    """
+    document = await document_store.get(str(document_id))
+    if document is None or (user_id is not None and document["uploaded_by"] != str(user_id)):
+        raise HTTPException(status_code=404, detail="Document not found")
+    return document
+
+
+async def _delete(document: dict) -> None:
+    """Remove a document's chunks, stored file and record."""
+    await qdrant_client.delete_document_chunks(document["id"])
+    minio_client.delete_file(document["minio_object_key"])
+    await document_store.delete(document["id"])
+    logger.info("Deleted document %s", document["id"])
+
+
 @router.post("/search")
 async def search_documents(request: dict) -> JSONResponse:  # type: ignore[type-arg]
     """Search documents using vector similarity."""
@@ -0,0 +1,159 @@
+"""Document records in the shared PostgreSQL `documents` table."""
+import logging
+from datetime import datetime
+from typing import Any
+from uuid import UUID
+
+from sqlalchemy import text
+from sqlalchemy.ext.asyncio import AsyncEngine, create_async_engine
+
+from src.config import settings
+
+logger = logging.getLogger(__name__)
+
+COLUMNS = (
+    "id, file_name, file_type, file_size, document_type, department, "
+    "minio_object_key, chunk_count, etl_status, etl_error, uploaded_by, "
+    "processed_at, created_at, updated_at"
+)
+
+# Columns `list_documents` can order by
+SORTABLE_COLUMNS = ("created_at", "file_name")
+
+_engine: AsyncEngine | None = None
+
+
+def get_engine() -> AsyncEngine:
+    """Create the database engine on first use."""
+    global _engine
+    if _engine is None:
+        _engine = create_async_engine(settings.database_url, pool_pre_ping=True)
+    return _engine
+
+
+def _to_dict(row: Any) -> dict[str, Any]:
+    """A result row as JSON-serializable values."""
+    record: dict[str, Any] = {}
+    for key, value in row._mapping.items():
+        if isinstance(value, UUID):
+            value = str(value)
+        elif isinstance(value, datetime):
+            value = value.isoformat()
+        record[key] = value
+    return record
+
+
+async def create(
+    document_id: str,
+    file_name: str,
+    file_type: str,
+    file_size: int,
+    document_type: str,
+    department: str,
+    object_key: str,
+    uploaded_by: str | None,
+) -> None:
+    """Record a newly uploaded document as `processing`."""
+    async with get_engine().begin() as conn:
+        await conn.execute(
+            text(
+                "INSERT INTO documents (id, file_name, file_type, file_size, document_type, "
+                "department, minio_object_key, uploaded_by, etl_status) "
+                "VALUES (CAST(:id AS uuid), :file_name, :file_type, :file_size, "
+                ":document_type, :department, :object_key, CAST(:uploaded_by AS uuid), "
+                "'processing')"
+            ),
+            {
+                "id": document_id,
+                "file_name": file_name,
+                "file_type": file_type,
+                "file_size": file_size,
+                "document_type": document_type or None,
+                "department": department or None,
+                "object_key": object_key,
+                "uploaded_by": uploaded_by,
+            },
+        )
+
+
+async def set_status(
+    document_id: str,
+    status: str,
+    chunk_count: int | None = None,
+    error: str | None = None,
+) -> None:
+    """Update the ingestion status; `completed` and `failed` also set `processed_at`."""
+    async with get_engine().begin() as conn:
+        await conn.execute(
+            text(
+                "UPDATE documents SET etl_status = :status, etl_error = :error, "
+                "chunk_count = COALESCE(:chunk_count, chunk_count), "
+                "processed_at = CASE WHEN :status IN ('completed', 'failed') "
+                "THEN NOW() ELSE processed_at END, "
+                "updated_at = NOW() "
+                "WHERE id = CAST(:id AS uuid)"
+            ),
+            {
+                "id": document_id,
+                "status": status,
+                "chunk_count": chunk_count,
+                "error": error,
+            },
+        )
+
+
+async def get(document_id: str) -> dict[str, Any] | None:
+    """The document record, or None when there is none."""
+    async with get_engine().connect() as conn:
+        result = await conn.execute(
+            text(f"SELECT {COLUMNS} FROM documents WHERE id = CAST(:id AS uuid)"),
+            {"id": document_id},
+        )
+        row = result.first()
+    return _to_dict(row) if row else None
+
+
+async def list_documents(
+    uploaded_by: str | None = None,
+    limit: int = 20,
+    offset: int = 0,
+    sort: str = "-created_at",
+) -> tuple[list[dict[str, Any]], int]:
+    """A page of documents and the total count; only those of `uploaded_by` when given.
+
+    `sort` names one of SORTABLE_COLUMNS, prefixed with `-` for descending order.
+    """
+    column = sort.removeprefix("-")
+    if column not in SORTABLE_COLUMNS:
+        raise ValueError(f"Unsortable column: {column}")
+    direction = "DESC" if sort.startswith("-") else "ASC"
+    where = (
+        "WHERE CAST(:uploaded_by AS uuid) IS NULL "
+        "OR uploaded_by = CAST(:uploaded_by AS uuid)"
+    )
+
+    async with get_engine().connect() as conn:
+        result = await conn.execute(
+            text(
+                f"SELECT {COLUMNS} FROM documents {where} "
+                f"ORDER BY {column} {direction}, id "
+                "LIMIT :limit OFFSET :offset"
+            ),
+            {"uploaded_by": uploaded_by, "limit": limit, "offset": offset},
+        )
+        documents = [_to_dict(row) for row in result]
+        total = await conn.scalar(
+            text(f"SELECT COUNT(*) FROM documents {where}"),
+            {"uploaded_by": uploaded_by},
+        )
+    return documents, total or 0
+
+
+async def delete(document_id: str) -> None:
+    """Remove the document record."""
+    async with get_engine().begin() as conn:
+        await conn.execute(
+            text("DELETE FROM documents WHERE id = CAST(:id AS uuid)"),
+            {"id": document_id},
+        )
+    logger.info("Deleted document record %s", document_id)
@@ -1,6 +1,7 @@
 """MinIO object storage client."""
 import io
 import logging
+from collections.abc import Iterator
 
 from minio import Minio
 
@@ -49,3 +50,29 @@ def download_file(object_key: str) -> bytes:
     response.close()
     response.release_conn()
     return data
+
+
+def stream_file(object_key: str, chunk_size: int = 64 * 1024) -> Iterator[bytes]:
+    """Open a file in MinIO and yield its content in chunks of `chunk_size`.
+
+    The object is opened before returning, so a missing file raises here
+    rather than once the chunks are consumed.
+    """
+    client = get_minio_client()
+    response = client.get_object(settings.minio_bucket, object_key)
+
+    def chunks() -> Iterator[bytes]:
+        try:
+            yield from response.stream(chunk_size)
+        finally:
+            response.close()
+            response.release_conn()
+
+    return chunks()
+
+
+def delete_file(object_key: str) -> None:
+    """Delete a file from MinIO."""
+    client = get_minio_client()
+    client.remove_object(settings.minio_bucket, object_key)
+    logger.info("Deleted %s from MinIO", object_key)
@@ -1,4 +1,4 @@
-"""Document parser supporting PDF and Word formats."""
+"""Document parser supporting PDF, Word and plain-text formats."""
 import io
 import logging
 from dataclasses import dataclass
@@ -90,6 +90,31 @@ def parse_docx(file_bytes: bytes, file_name: str) -> ParsedDocument:
     )
 
 
+def parse_text(file_bytes: bytes, file_name: str, file_type: str) -> ParsedDocument:
+    """Read a plain-text or Markdown file; blank-line separated blocks become pages.
+
+    UTF-8 is tried first, with or without a BOM, then Shift_JIS (cp932) as
+    older Windows editors save it.
+    """
+    try:
+        text = file_bytes.decode("utf-8-sig")
+    except UnicodeDecodeError:
+        text = file_bytes.decode("cp932", errors="replace")
+    text = text.replace("\r\n", "\n").strip()
+    pages = [block.strip() for block in text.split("\n\n") if block.strip()]
+
+    return ParsedDocument(
+        text=text,
+        metadata={
+            "file_name": file_name,
+            "file_type": file_type,
+            "page_count": len(pages),
+            "title": None,
+        },
+        pages=pages,
+    )
+
+
 def _table_to_markdown(table) -> str:  # type: ignore[no-untyped-def]
     """Convert a docx table to markdown format."""
     rows: list[str] = []
@@ -108,5 +133,7 @@ def parse_document(file_bytes: bytes, file_name: str) -> ParsedDocument:
         return parse_pdf(file_bytes, file_name)
     elif ext in ("docx", "doc"):
         return parse_docx(file_bytes, file_name)
+    elif ext in ("txt", "md"):
+        return parse_text(file_bytes, file_name, ext)
     else:
         raise ValueError(f"Unsupported file type: {ext}")
@@ -4,41 +4,108 @@ from uuid import uuid4
 
 from src.services.parser import parse_document
 from src.services.chunker import chunk_text
-from src.services import embedding, qdrant_client, minio_client
+from src.services import document_store, embedding, qdrant_client, minio_client
 
 logger = logging.getLogger(__name__)
 
+CONTENT_TYPES = {
+    "pdf": "application/pdf",
+    "docx": "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "txt": "text/plain",
+    "md": "text/markdown",
+}
+
 
 async def process_document(
     file_bytes: bytes,
     file_name: str,
     document_type: str = "",
     department: str = "",
+    uploaded_by: str | None = None,
 ) -> dict[str, str | int]:
     """Full ETL pipeline for a document.
     
     Steps:
-    1. Upload to MinIO
+    1. Upload to MinIO and record the document as `processing`
     2. Parse document
     3. Chunk text
     4. Generate embeddings
     5. Store in Qdrant
+
+    The record ends up `completed`, or `failed` with the error when a later step raises.
     """
     document_id = str(uuid4())
     ext = file_name.rsplit(".", 1)[-1].lower()
 
-    content_types = {
-        "pdf": "application/pdf",
-        "docx": "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
-    }
-
     # Step 1: Upload to MinIO
     object_key = f"{document_id}/{file_name}"
     minio_client.upload_file(
-        object_key, file_bytes, content_types.get(ext, "application/octet-stream")
+        object_key, file_bytes, CONTENT_TYPES.get(ext, "application/octet-stream")
     )
     logger.info("[%s] Uploaded to MinIO: %s", document_id, object_key)
+    await document_store.create(
+        document_id,
+        file_name,
+        ext,
+        len(file_bytes),
+        document_type,
+        department,
+        object_key,
+        uploaded_by,
+    )
+
+    try:
+        count = await _ingest(document_id, file_bytes, file_name, ext, document_type, department)
+    except Exception as e:
+        await document_store.set_status(document_id, "failed", error=str(e))
+        raise
+    await document_store.set_status(document_id, "completed", chunk_count=count)
+
+    return {
+        "document_id": document_id,
+        "status": "completed",
+        "chunk_count": count,
+        "minio_object_key": object_key,
+    }
+
+
+async def reprocess_document(document: dict) -> dict[str, str | int]:
+    """Re-run ingestion of a stored document from its file in MinIO.
 
+    The document's old chunks are replaced; like `process_document` the record
+    ends up `completed`, or `failed` with the error.
+    """
+    document_id = document["id"]
+    file_bytes = minio_client.download_file(document["minio_object_key"])
+    await document_store.set_status(document_id, "processing")
+    await qdrant_client.delete_document_chunks(document_id)
+
+    try:
+        count = await _ingest(
+            document_id,
+            file_bytes,
+            document["file_name"],
+            document["file_type"],
+            document.get("document_type") or "",
+            document.get("department") or "",
+        )
+    except Exception as e:
+        await document_store.set_status(document_id, "failed", error=str(e))
+        raise
+    await document_store.set_status(document_id, "completed", chunk_count=count)
+
+    return {"document_id": document_id, "status": "completed", "chunk_count": count}
+
+
+async def _ingest(
+    document_id: str,
+    file_bytes: bytes,
+    file_name: str,
+    ext: str,
+    document_type: str,
+    department: str,
+) -> int:
+    """Parse, chunk, embed and store a document; returns the stored chunk count."""
     # Step 2: Parse document
     parsed = parse_document(file_bytes, file_name)
     logger.info(
@@ -51,12 +118,7 @@ async def process_document(
     logger.info("[%s] Chunked into %d chunks", document_id, len(chunks))
 
     if not chunks:
-        return {
-            "document_id": document_id,
-            "status": "completed",
-            "chunk_count": 0,
-            "minio_object_key": object_key,
-        }
+        return 0
 
     # Step 4: Generate embeddings
     texts = [c["text"] for c in chunks]
@@ -81,10 +143,4 @@ async def process_document(
         document_id, chunks, all_embeddings, metadata
     )
     logger.info("[%s] Stored %d chunks in Qdrant", document_id, count)
-
-    return {
-        "document_id": document_id,
-        "status": "completed",
-        "chunk_count": count,
-        "minio_object_key": object_key,
-    }
+    return count
@@ -0,0 +1,238 @@
+from typing import Any
+
+import pytest
+from fastapi.testclient import TestClient
+
+from src.main import app
+from src.routes import documents
+
+client = TestClient(app)
+
+DOCUMENT_ID = "7f6f6d5c-7a35-4d8e-9a7b-0d6c3f0e2a11"
+OWNER_ID = "3b1f0c2e-5d4a-4e8b-9c7d-1a2b3c4d5e6f"
+DOCUMENT = {
+    "id": DOCUMENT_ID,
+    "file_name": "manual.pdf",
+    "minio_object_key": f"{DOCUMENT_ID}/manual.pdf",
+    "etl_status": "completed",
+    "uploaded_by": OWNER_ID,
+}
+
+
+@pytest.fixture
+def store(monkeypatch: pytest.MonkeyPatch) -> dict[str, Any]:
+    """An in-memory document store holding DOCUMENT, with Qdrant and MinIO stubbed out."""
+    records: dict[str, Any] = {DOCUMENT_ID: dict(DOCUMENT)}
+    removed: dict[str, Any] = {"chunks": [], "files": []}
+    listed: dict[str, Any] = {"calls": []}
+
+    async def get(document_id: str) -> dict[str, Any] | None:
+        return records.get(document_id)
+
+    async def list_documents(
+        uploaded_by: str | None = None,
+        limit: int = 20,
+        offset: int = 0,
+        sort: str = "-created_at",
+    ) -> tuple[list[dict[str, Any]], int]:
+        listed["calls"].append({"limit": limit, "offset": offset, "sort": sort})
+        matching = [
+            record
+            for record in records.values()
+            if uploaded_by is None or record["uploaded_by"] == uploaded_by
+        ]
+        return matching[offset : offset + limit], len(matching)
+
+    async def delete(document_id: str) -> None:
+        records.pop(document_id, None)
+
+    async def delete_document_chunks(document_id: str) -> None:
+        removed["chunks"].append(document_id)
+
+    monkeypatch.setattr(documents.document_store, "get", get)
+    monkeypatch.setattr(documents.document_store, "list_documents", list_documents)
+    monkeypatch.setattr(documents.document_store, "delete", delete)
+    monkeypatch.setattr(
+        documents.qdrant_client, "delete_document_chunks", delete_document_chunks
+    )
+    monkeypatch.setattr(documents.minio_client, "delete_file", removed["files"].append)
+    return {"records": records, "removed": removed, "listed": listed}
+
+
+def test_list_is_filtered_by_uploader(store: dict[str, Any]) -> None:
+    everyone = client.get("/api/v1/documents").json()
+    assert everyone["data"] == [DOCUMENT]
+    assert everyone["meta"]["total"] == 1
+
+    own = client.get("/api/v1/documents", params={"user_id": OWNER_ID}).json()
+    assert own["data"] == [DOCUMENT]
+
+    other = client.get(
+        "/api/v1/documents", params={"user_id": "00000000-0000-0000-0000-000000000000"}
+    ).json()
+    assert other["data"] == []
+
+
+def test_list_passes_paging_and_sort_to_the_store(store: dict[str, Any]) -> None:
+    response = client.get(
+        "/api/v1/documents", params={"limit": 5, "offset": 10, "sort": "file_name"}
+    )
+    assert response.status_code == 200
+    assert response.json()["meta"] == {"total": 1, "limit": 5, "offset": 10}
+    assert store["listed"]["calls"] == [{"limit": 5, "offset": 10, "sort": "file_name"}]
+
+
+def test_list_rejects_an_unknown_sort_column(store: dict[str, Any]) -> None:
+    response = client.get("/api/v1/documents", params={"sort": "-etl_error"})
+    assert response.status_code == 400
+    assert store["listed"]["calls"] == []
+
+
+def test_get_document_returns_its_record(store: dict[str, Any]) -> None:
+    response = client.get(f"/api/v1/documents/{DOCUMENT_ID}")
+    assert response.status_code == 200
+    assert response.json()["data"] == DOCUMENT
+
+
+def test_get_unknown_document_is_not_found(store: dict[str, Any]) -> None:
+    response = client.get("/api/v1/documents/00000000-0000-0000-0000-000000000000")
+    assert response.status_code == 404
+
+
+def test_status_reports_the_ingestion_outcome(store: dict[str, Any]) -> None:
+    completed = client.get(f"/api/v1/documents/{DOCUMENT_ID}/status").json()["data"]
+    assert completed["status"] == "completed"
+    assert completed["progress"] == 100
+
+    store["records"][DOCUMENT_ID].update(etl_status="failed", etl_error="Unreadable PDF")
+    failed = client.get(f"/api/v1/documents/{DOCUMENT_ID}/status").json()["data"]
+    assert failed["status"] == "failed"
+    assert failed["error"] == "Unreadable PDF"
+
+
+def test_status_of_unknown_document_is_not_found(store: dict[str, Any]) -> None:
+    response = client.get("/api/v1/documents/00000000-0000-0000-0000-000000000000/status")
+    assert response.status_code == 404
+
+
+def test_delete_document_removes_chunks_file_and_record(store: dict[str, Any]) -> None:
+    response = client.delete(f"/api/v1/documents/{DOCUMENT_ID}")
+    assert response.status_code == 200
+    assert response.json()["data"] == {"document_id": DOCUMENT_ID, "deleted": True}
+    assert store["removed"] == {
+        "chunks": [DOCUMENT_ID],
+        "files": [f"{DOCUMENT_ID}/manual.pdf"],
+    }
+    assert DOCUMENT_ID not in store["records"]
+
+
+def test_delete_unknown_document_is_not_found(store: dict[str, Any]) -> None:
+    response = client.delete("/api/v1/documents/00000000-0000-0000-0000-000000000000")
+    assert response.status_code == 404
+    assert store["removed"] == {"chunks": [], "files": []}
+
+
+def test_delete_user_documents_removes_only_theirs(store: dict[str, Any]) -> None:
+    other_id = "9c8b7a6d-5e4f-4a3b-8c2d-1e0f9a8b7c6d"
+    store["records"][other_id] = dict(
+        DOCUMENT,
+        id=other_id,
+        minio_object_key=f"{other_id}/notes.docx",
+        uploaded_by="00000000-0000-0000-0000-000000000000",
+    )
+
+    response = client.delete(f"/api/v1/users/{OWNER_ID}/documents")
+    assert response.status_code == 200
+    assert response.json()["data"] == {"user_id": OWNER_ID, "deleted_documents": 1}
+    assert store["removed"]["chunks"] == [DOCUMENT_ID]
+    assert list(store["records"]) == [other_id]
+
+
+def test_delete_user_documents_without_any_is_a_no_op(store: dict[str, Any]) -> None:
+    response = client.delete(
+        "/api/v1/users/00000000-0000-0000-0000-000000000001/documents"
+    )
+    assert response.status_code == 200
+    assert response.json()["data"]["deleted_documents"] == 0
+    assert store["removed"] == {"chunks": [], "files": []}
+
+
+def test_reprocess_runs_the_pipeline_again(
+    store: dict[str, Any], monkeypatch: pytest.MonkeyPatch
+) -> None:
+    reprocessed: list[str] = []
+
+    async def reprocess_document(document: dict[str, Any]) -> dict[str, Any]:
+        reprocessed.append(document["id"])
+        return {"document_id": document["id"], "status": "completed", "chunk_count": 3}
+
+    monkeypatch.setattr(documents.pipeline, "reprocess_document", reprocess_document)
+    response = client.post(f"/api/v1/documents/{DOCUMENT_ID}/reprocess")
+    assert response.status_code == 200
+    assert response.json()["data"]["chunk_count"] == 3
+    assert reprocessed == [DOCUMENT_ID]
+
+
+def test_reprocess_while_processing_is_a_conflict(store: dict[str, Any]) -> None:
+    store["records"][DOCUMENT_ID]["etl_status"] = "processing"
+    response = client.post(f"/api/v1/documents/{DOCUMENT_ID}/reprocess")
+    assert response.status_code == 409
+
+
+def test_reprocess_unknown_document_is_not_found(store: dict[str, Any]) -> None:
+    response = client.post(
+        "/api/v1/documents/00000000-0000-0000-0000-000000000000/reprocess"
+    )
+    assert response.status_code == 404
+
+
+def test_file_is_streamed_as_an_attachment(
+    store: dict[str, Any], monkeypatch: pytest.MonkeyPatch
+) -> None:
+    opened: list[str] = []
+
+    def stream_file(object_key: str) -> Any:
+        opened.append(object_key)
+        return iter([b"%PDF-", b"1.7"])
+
+    monkeypatch.setattr(documents.minio_client, "stream_file", stream_file)
+    store["records"][DOCUMENT_ID].update(file_name="手順書.pdf", file_type="pdf", file_size=8)
+
+    response = client.get(f"/api/v1/documents/{DOCUMENT_ID}/file")
+    assert response.status_code == 200
+    assert response.content == b"%PDF-1.7"
+    assert response.headers["content-type"] == "application/pdf"
+    assert response.headers["content-length"] == "8"
+    assert response.headers["content-disposition"] == (
+        "attachment; filename=\"___.pdf\"; filename*=UTF-8''%E6%89%8B%E9%A0%86%E6%9B%B8.pdf"
+    )
+    assert opened == [f"{DOCUMENT_ID}/manual.pdf"]
+
+
+def test_file_of_unknown_document_is_not_found(store: dict[str, Any]) -> None:
+    response = client.get("/api/v1/documents/00000000-0000-0000-0000-000000000000/file")
+    assert response.status_code == 404
+
+
+@pytest.mark.parametrize(
+    ("method", "path"),
+    [
+        ("GET", ""),
+        ("GET", "/status"),
+        ("GET", "/file"),
+        ("POST", "/reprocess"),
+        ("DELETE", ""),
+    ],
+)
+def test_document_of_another_uploader_is_not_found(
+    store: dict[str, Any], method: str, path: str
+) -> None:
+    other = {"user_id": "00000000-0000-0000-0000-000000000001"}
+    response = client.request(method, f"/api/v1/documents/{DOCUMENT_ID}{path}", params=other)
+    assert response.status_code == 404
+    assert DOCUMENT_ID in store["records"]
+
+
+def test_document_of_its_uploader_is_found(store: dict[str, Any]) -> None:
+    response = client.get(f"/api/v1/documents/{DOCUMENT_ID}", params={"user_id": OWNER_ID})
+    assert response.status_code == 200
@@ -0,0 +1,19 @@
+from src.services.parser import parse_document
+
+
+def test_markdown_is_split_into_blocks() -> None:
+    parsed = parse_document(b"# Setup\r\n\r\nPlug it in.\n\n\nTurn it on.\n", "guide.md")
+    assert parsed.text == "# Setup\n\nPlug it in.\n\n\nTurn it on."
+    assert parsed.pages == ["# Setup", "Plug it in.", "Turn it on."]
+    assert parsed.metadata["file_type"] == "md"
+
+
+def test_text_falls_back_to_shift_jis() -> None:
+    parsed = parse_document("手順書".encode("cp932"), "notes.txt")
+    assert parsed.text == "手順書"
+    assert parsed.metadata["file_type"] == "txt"
+
+
+def test_utf8_byte_order_mark_is_dropped() -> None:
+    parsed = parse_document("\ufeff手順書".encode("utf-8"), "notes.txt")
+    assert parsed.text == "手順書"
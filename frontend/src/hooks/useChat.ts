@@ -62,6 +62,7 @@ export function useChat() {
 
       let accumulatedContent = ''
       let sources: Source[] = []
+      let eventName = 'message'
 
       while (true) {
         const { done, value } = await reader.read()
@@ -71,27 +72,32 @@ export function useChat() {
         const lines = text.split('\n')
 
         for (const line of lines) {
-          if (line.startsWith('data: ')) {
+          if (line.startsWith('event: ')) {
+            eventName = line.slice(7).trim()
+          } else if (line.startsWith('data: ')) {
             try {
               const data = JSON.parse(line.slice(6))
 
-              if (data.content) {
-                accumulatedContent += data.content
-                setMessages((prev) =>
-                  prev.map((m) =>
-                    m.id === assistantMsg.id
-                      ? { ...m, content: accumulatedContent }
-                      : m,
-                  ),
-                )
-              }
-
-              if (data.sources) {
-                sources = data.sources
+              switch (eventName) {
+                case 'sources':
+                  sources = data
+                  break
+                case 'token':
+                  accumulatedContent += data
+                  setMessages((prev) =>
+                    prev.map((m) =>
+                      m.id === assistantMsg.id
+                        ? { ...m, content: accumulatedContent }
+                        : m,
+                    ),
+                  )
+                  break
               }
             } catch {
               // Skip non-JSON lines
             }
+          } else if (line.trim() === '') {
+            eventName = 'message'
           }
         }
       }
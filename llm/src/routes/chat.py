@@ -16,6 +16,10 @@ class ChatRequest(BaseModel):
     query: str
     chat_session_id: str | None = None
     context: list[str] = []
+    # Replaces DEFAULT_SYSTEM_PROMPT when set by the gateway
+    system_prompt: str | None = None
+    # Added to the system prompt, e.g. asking for [n] citations of the numbered context
+    instructions: list[str] = []
 
 
 @router.post("/chat/stream")
@@ -23,7 +27,13 @@ async def chat_stream(request: ChatRequest) -> EventSourceResponse:
     async def generate() -> Any:
         yield {"event": "start", "data": json.dumps({"status": "generating"})}
 
-        prompt = _build_prompt(request.query, request.context)
+        prompt = _build_prompt(
+            request.query,
+            request.context,
+            request.system_prompt,
+            request.instructions,
+        )
+        usage: dict[str, int] | None = None
 
         try:
             async with httpx.AsyncClient(timeout=120.0) as client:
@@ -46,6 +56,13 @@ async def chat_stream(request: ChatRequest) -> EventSourceResponse:
                                 "data": json.dumps({"content": token}),
                             }
                         if data.get("done", False):
+                            prompt_tokens = data.get("prompt_eval_count", 0)
+                            completion_tokens = data.get("eval_count", 0)
+                            usage = {
+                                "prompt_tokens": prompt_tokens,
+                                "completion_tokens": completion_tokens,
+                                "total_tokens": prompt_tokens + completion_tokens,
+                            }
                             break
         except Exception as e:
             yield {
@@ -53,19 +70,72 @@ async def chat_stream(request: ChatRequest) -> EventSourceResponse:
                 "data": json.dumps({"message": str(e)}),
             }
 
-        yield {"event": "done", "data": json.dumps({"status": "complete"})}
+        done: dict[str, Any] = {"status": "complete"}
+        if usage is not None:
+            done["usage"] = usage
+        yield {"event": "done", "data": json.dumps(done)}
 
     return EventSourceResponse(generate())
 
 
-def _build_prompt(query: str, context: list[str]) -> str:
-    system_prompt = (
-        "あなたは生産工場のナレッジベースアシスタントです。\n"
-        "提供された情報源のみに基づいて回答してください。\n"
-        "情報源にない内容は「該当する情報が見つかりませんでした」と回答してください。\n"
-        "日本語で回答してください。\n"
+class RewriteRequest(BaseModel):
+    query: str
+
+
+class RewriteResponse(BaseModel):
+    query: str
+
+
+@router.post("/query/rewrite")
+async def rewrite_query(request: RewriteRequest) -> RewriteResponse:
+    """Rewrite a user question into a search query for document retrieval."""
+    try:
+        async with httpx.AsyncClient(timeout=30.0) as client:
+            resp = await client.post(
+                f"{settings.ollama_host}/api/generate",
+                json={
+                    "model": settings.llm_model,
+                    "prompt": _build_rewrite_prompt(request.query),
+                    "stream": False,
+                },
+            )
+            resp.raise_for_status()
+            rewritten = resp.json().get("response", "").strip()
+    except httpx.HTTPStatusError as e:
+        raise HTTPException(status_code=502, detail=f"Ollama error: {e.response.text}")
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=f"Query rewrite failed: {e}")
+
+    return RewriteResponse(query=rewritten or request.query)
+
+
+def _build_rewrite_prompt(query: str) -> str:
+    return (
+        "以下の質問を、社内文書の検索に適した検索クエリに書き換えてください。\n"
+        "誤字を修正し、設備名・工程名などのキーワードを残してください。\n"
+        "検索クエリのみを 1 行で出力してください。\n\n"
+        f"【質問】\n{query}"
     )
 
+
+DEFAULT_SYSTEM_PROMPT = (
+    "あなたは生産工場のナレッジベースアシスタントです。\n"
+    "提供された情報源のみに基づいて回答してください。\n"
+    "情報源にない内容は「該当する情報が見つかりませんでした」と回答してください。\n"
+    "日本語で回答してください。\n"
+)
+
+
+def _build_prompt(
+    query: str,
+    context: list[str],
+    system_prompt: str | None = None,
+    instructions: list[str] | None = None,
+) -> str:
+    system_prompt = system_prompt or DEFAULT_SYSTEM_PROMPT
+    if instructions:
+        system_prompt = system_prompt.rstrip() + "\n" + "\n".join(instructions) + "\n"
+
     context_text = "\n\n".join(context) if context else "情報源なし"
 
     return (
@@ -26,3 +26,24 @@ async def health_check() -> JSONResponse:
             "model": settings.llm_model,
         }
     )
+
+
+@router.get("/status")
+async def model_status() -> JSONResponse:
+    """Report whether the configured model is available in Ollama."""
+    model_loaded = False
+    try:
+        async with httpx.AsyncClient(timeout=5.0) as client:
+            resp = await client.get(f"{settings.ollama_host}/api/tags")
+            resp.raise_for_status()
+            names = {m.get("name") for m in resp.json().get("models", [])}
+            model_loaded = settings.llm_model in names
+    except Exception:
+        pass
+
+    return JSONResponse(
+        content={
+            "model_loaded": model_loaded,
+            "model": settings.llm_model,
+        }
+    )